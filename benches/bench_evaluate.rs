@@ -5,12 +5,13 @@ use nucleotide_converter::{CodeConverter, CodeConverterInPlace};
 use rand::{Rng, SeedableRng};
 
 fn generate_code(rng: &mut impl Rng, out: &mut [u8]) {
-    for i in 0..out.len() {
-        out[i] = b"ATCGatcgNn"[rng.random_range(0..10)];
+    for b in out.iter_mut() {
+        *b = b"ATCGatcgNn"[rng.random_range(0..10)];
     }
 }
 
 #[derive(Debug, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
 enum Converter {
     Naive,
     NaiveToLower,
@@ -20,6 +21,29 @@ enum Converter {
     SSE41,
     AVX2,
     AVX512VBMI,
+    NEON,
+}
+
+impl Converter {
+    fn is_supported(self) -> bool {
+        match self {
+            Converter::Naive | Converter::NaiveToLower | Converter::LUT => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Converter::SSE2 => true,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Converter::SSSE3 => std::arch::is_x86_feature_detected!("ssse3"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Converter::SSE41 => std::arch::is_x86_feature_detected!("sse4.1"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Converter::AVX2 => std::arch::is_x86_feature_detected!("avx2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Converter::AVX512VBMI => std::arch::is_x86_feature_detected!("avx512vbmi"),
+            #[cfg(target_arch = "aarch64")]
+            Converter::NEON => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
 }
 
 struct Input {
@@ -43,24 +67,15 @@ fn benchmark_code_converter(c: &mut Criterion) {
             Converter::Naive,
             Converter::LUT,
             Converter::SSE2,
+            Converter::SSSE3,
+            Converter::AVX2,
+            Converter::AVX512VBMI,
+            Converter::NEON,
         ]
         .into_iter()
-        .chain(
-            core::iter::once(Converter::SSSE3)
-                .filter(|_| std::arch::is_x86_feature_detected!("ssse3")),
-        )
-        .chain(
-            core::iter::once(Converter::AVX2)
-                .filter(|_| std::arch::is_x86_feature_detected!("avx2")),
-        )
-        .chain(
-            core::iter::once(Converter::AVX512VBMI)
-                .filter(|_| std::arch::is_x86_feature_detected!("avx512vbmi")),
-        ) {
-            let input = Input {
-                name: converter,
-                n: n,
-            };
+        .filter(|c| c.is_supported())
+        {
+            let input = Input { name: converter, n };
             g.throughput(Throughput::Elements(input.n as u64));
             let mut code = vec![0; input.n];
             g.bench_with_input(
@@ -75,10 +90,12 @@ fn benchmark_code_converter(c: &mut Criterion) {
 
                     b.iter(|| {
                         match input.name {
+                            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                             Converter::SSE2 => {
                                 let converter = nucleotide_converter::SSE2CodeConverter::default();
                                 converter.convert(&code, &mut out);
                             }
+                            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                             Converter::SSSE3 => {
                                 let converter = nucleotide_converter::SSSE3CodeConverter::default();
                                 converter.convert(&code, &mut out);
@@ -96,15 +113,22 @@ fn benchmark_code_converter(c: &mut Criterion) {
                                 let converter = nucleotide_converter::NaiveCodeConverter::default();
                                 converter.convert(&code, &mut out);
                             }
+                            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                             Converter::AVX2 => {
                                 let converter = nucleotide_converter::AVX2CodeConverter::default();
                                 converter.convert(&code, &mut out);
                             }
+                            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                             Converter::AVX512VBMI => {
                                 let converter =
                                     nucleotide_converter::AVX512VbmiCodeConverter::default();
                                 converter.convert(&code, &mut out);
                             }
+                            #[cfg(target_arch = "aarch64")]
+                            Converter::NEON => {
+                                let converter = nucleotide_converter::NEONCodeConverter::default();
+                                converter.convert(&code, &mut out);
+                            }
                             _ => unimplemented!(),
                         }
                         core::hint::black_box(&out);
@@ -137,14 +161,17 @@ fn benchmark_custom_alphabet_converter(c: &mut Criterion) {
 
     let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
     for n in [3_000_000, 100_000_000] {
-        for converter in [Converter::LUT].into_iter().chain(
-            core::iter::once(Converter::SSE41)
-                .filter(|_| std::arch::is_x86_feature_detected!("sse4.1")),
-        ) {
-            let input = Input {
-                name: converter,
-                n: n,
-            };
+        for converter in [
+            Converter::LUT,
+            Converter::SSE41,
+            Converter::AVX2,
+            Converter::AVX512VBMI,
+            Converter::NEON,
+        ]
+        .into_iter()
+        .filter(|c| c.is_supported())
+        {
+            let input = Input { name: converter, n };
             g.throughput(Throughput::Elements(input.n as u64));
             let mut code = vec![0; input.n];
             let mut out = vec![0; input.n];
@@ -161,12 +188,34 @@ fn benchmark_custom_alphabet_converter(c: &mut Criterion) {
                             converter.convert(&code, &mut out);
                             black_box(&mut out);
                         }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                         Converter::SSE41 => {
                             let converter =
                                 nucleotide_converter::custom_alphabet::SSE41Packer::default();
                             converter.convert(&code, &mut out);
                             black_box(&mut out);
                         }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX2 => {
+                            let converter =
+                                nucleotide_converter::custom_alphabet::AVX2Packer::default();
+                            converter.convert(&code, &mut out);
+                            black_box(&mut out);
+                        }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX512VBMI => {
+                            let converter =
+                                nucleotide_converter::custom_alphabet::AVX512VbmiPacker::default();
+                            converter.convert(&code, &mut out);
+                            black_box(&mut out);
+                        }
+                        #[cfg(target_arch = "aarch64")]
+                        Converter::NEON => {
+                            let converter =
+                                nucleotide_converter::custom_alphabet::NEONPacker::default();
+                            converter.convert(&code, &mut out);
+                            black_box(&mut out);
+                        }
                         _ => unimplemented!(),
                     });
                 },
@@ -185,6 +234,7 @@ fn benchmark_custom_alphabet_converter(c: &mut Criterion) {
                                     .convert_in_place(&mut code),
                             );
                         }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                         Converter::SSE41 => {
                             black_box(
                                 nucleotide_converter::custom_alphabet::SSE41InPlacePacker::default(
@@ -192,6 +242,27 @@ fn benchmark_custom_alphabet_converter(c: &mut Criterion) {
                                 .convert_in_place(&mut code),
                             );
                         }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX2 => {
+                            black_box(
+                                nucleotide_converter::custom_alphabet::AVX2InPlacePacker::default()
+                                    .convert_in_place(&mut code),
+                            );
+                        }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX512VBMI => {
+                            black_box(
+                                nucleotide_converter::custom_alphabet::AVX512VbmiInPlacePacker::default()
+                                    .convert_in_place(&mut code),
+                            );
+                        }
+                        #[cfg(target_arch = "aarch64")]
+                        Converter::NEON => {
+                            black_box(
+                                nucleotide_converter::custom_alphabet::NEONInPlacePacker::default()
+                                    .convert_in_place(&mut code),
+                            );
+                        }
                         _ => unimplemented!(),
                     });
                 },
@@ -201,20 +272,119 @@ fn benchmark_custom_alphabet_converter(c: &mut Criterion) {
                 &input,
                 |b, _input: &Input| {
                     generate_code(&mut rng, &mut code);
-                    let code = nucleotide_converter::custom_alphabet::SSE41InPlacePacker::default()
+                    let code = nucleotide_converter::custom_alphabet::LUTInPlacePacker::default()
                         .convert_in_place(&mut code);
 
                     b.iter(|| match converter {
                         Converter::LUT => {
                             let converter =
                                 nucleotide_converter::custom_alphabet::LUTUnpacker::default();
-                            converter.convert(&code, &mut out);
+                            converter.convert(code, &mut out);
                             black_box(&mut out);
                         }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
                         Converter::SSE41 => {
                             let converter =
                                 nucleotide_converter::custom_alphabet::SSSE3Unpacker::default();
-                            converter.convert(&code, &mut out);
+                            converter.convert(code, &mut out);
+                            black_box(&mut out);
+                        }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX2 => {
+                            let converter =
+                                nucleotide_converter::custom_alphabet::AVX2Unpacker::default();
+                            converter.convert(code, &mut out);
+                            black_box(&mut out);
+                        }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX512VBMI => {
+                            let converter =
+                                nucleotide_converter::custom_alphabet::AVX512VbmiUnpacker::default(
+                                );
+                            converter.convert(code, &mut out);
+                            black_box(&mut out);
+                        }
+                        #[cfg(target_arch = "aarch64")]
+                        Converter::NEON => {
+                            let converter =
+                                nucleotide_converter::custom_alphabet::NEONUnpacker::default();
+                            converter.convert(code, &mut out);
+                            black_box(&mut out);
+                        }
+                        _ => unimplemented!(),
+                    });
+                },
+            );
+        }
+    }
+}
+
+fn benchmark_two_bit(c: &mut Criterion) {
+    use nucleotide_converter::two_bit::TwoBitPack;
+
+    let mut g = c.benchmark_group("two_bit");
+
+    let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+    for n in [3_000_000, 100_000_000] {
+        for converter in [Converter::LUT, Converter::AVX2]
+            .into_iter()
+            .filter(|c| c.is_supported())
+        {
+            let input = Input { name: converter, n };
+            g.throughput(Throughput::Elements(input.n as u64));
+            let mut code = vec![0; input.n];
+            let mut packed = vec![0; input.n.div_ceil(4)];
+            let mut mask = vec![0; input.n.div_ceil(8)];
+            let mut out = vec![0; input.n];
+            g.bench_with_input(
+                BenchmarkId::new("two_bit_pack_masked", &input),
+                &input,
+                |b, _input: &Input| {
+                    generate_code(&mut rng, &mut code);
+
+                    b.iter(|| match converter {
+                        Converter::LUT => {
+                            nucleotide_converter::two_bit::TwoBitPacker::default().pack_masked(
+                                &code,
+                                &mut packed,
+                                &mut mask,
+                            );
+                            black_box((&mut packed, &mut mask));
+                        }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX2 => {
+                            nucleotide_converter::two_bit::AVX2TwoBitPacker::default().pack_masked(
+                                &code,
+                                &mut packed,
+                                &mut mask,
+                            );
+                            black_box((&mut packed, &mut mask));
+                        }
+                        _ => unimplemented!(),
+                    });
+                },
+            );
+            g.bench_with_input(
+                BenchmarkId::new("two_bit_unpack", &input),
+                &input,
+                |b, _input: &Input| {
+                    generate_code(&mut rng, &mut code);
+                    nucleotide_converter::two_bit::TwoBitPacker::default().pack_substitute(
+                        &code,
+                        &mut packed,
+                        0,
+                    );
+
+                    b.iter(|| match converter {
+                        Converter::LUT => {
+                            nucleotide_converter::two_bit::TwoBitUnpacker::default()
+                                .convert(&packed, &mut out);
+                            black_box(&mut out);
+                        }
+                        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                        Converter::AVX2 => {
+                            nucleotide_converter::two_bit::AVX2TwoBitUnpacker::default()
+                                .convert(&packed, &mut out);
                             black_box(&mut out);
                         }
                         _ => unimplemented!(),
@@ -228,6 +398,7 @@ fn benchmark_custom_alphabet_converter(c: &mut Criterion) {
 criterion_group!(
     benches,
     benchmark_code_converter,
-    benchmark_custom_alphabet_converter
+    benchmark_custom_alphabet_converter,
+    benchmark_two_bit
 );
 criterion_main!(benches);
@@ -0,0 +1,451 @@
+//! BAM 4-bit sequences.
+//!
+//! BAM records store read sequences as the codes `=ACMGRSVTWYHKDBN`, two bases per byte with the
+//! first base in the high nibble. These codes are the [`custom_alphabet`](crate::custom_alphabet)
+//! masks, A = 1, C = 2, G = 4 and T = 8, so converting between the two is a nibble swap within
+//! every byte, see [`LUTBamNibbleConverter`].
+
+use crate::{CodeConverter, CodeConverterInPlace};
+
+/// The base of every BAM code.
+pub const BAM_CODE_TO_CHAR: [u8; 16] = *b"=ACMGRSVTWYHKDBN";
+
+/// The BAM code of every ASCII byte, as `seq_nt16_table` in htslib: `=` is 0, U is T and bytes
+/// that are not IUPAC codes are N.
+pub const BAM_CHAR_TO_CODE: [u8; 256] = {
+    let mut table = [15; 256];
+    let mut code = 0;
+    while code < 16 {
+        let c = BAM_CODE_TO_CHAR[code];
+        table[c as usize] = code as u8;
+        table[c.to_ascii_lowercase() as usize] = code as u8;
+        code += 1;
+    }
+    table[b'U' as usize] = 8;
+    table[b'u' as usize] = 8;
+    table
+};
+
+/// An unpacker for BAM sequences, which carry their length separately.
+pub trait BamUnpack {
+    /// Unpack both nibbles of every byte of `seq` into `out[..seq.len() * 2]`.
+    fn unpack_bytes(&self, seq: &[u8], out: &mut [u8]);
+
+    /// Unpack the `len` bases of `seq` into the start of `out`.
+    ///
+    /// Panics if `seq` is not exactly `len.div_ceil(2)` bytes long.
+    fn unpack(&self, seq: &[u8], len: usize, out: &mut [u8]) {
+        assert_eq!(seq.len(), len.div_ceil(2));
+        assert!(out.len() >= len);
+
+        let (full, last) = seq.split_at(len / 2);
+        self.unpack_bytes(full, out);
+        if let Some(last) = last.first() {
+            let mut pair = [0; 2];
+            self.unpack_bytes(&[*last], &mut pair);
+            out[len - 1] = pair[0];
+        }
+    }
+}
+
+/// Packs ASCII into BAM codes. For odd lengths the low nibble of the last byte is 0.
+pub struct LUTBamPacker {
+    lut: [u8; 256],
+}
+
+impl Default for LUTBamPacker {
+    fn default() -> Self {
+        Self {
+            lut: BAM_CHAR_TO_CODE,
+        }
+    }
+}
+
+impl CodeConverter for LUTBamPacker {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        assert!(out.len() >= code.len().div_ceil(2));
+
+        for (pair, out) in code.chunks(2).zip(out) {
+            let high = self.lut[pair[0] as usize];
+            let low = pair.get(1).map_or(0, |c| self.lut[*c as usize]);
+            *out = high << 4 | low;
+        }
+    }
+}
+
+pub struct LUTBamUnpacker {
+    lut: [u8; 16],
+}
+
+impl Default for LUTBamUnpacker {
+    fn default() -> Self {
+        Self {
+            lut: BAM_CODE_TO_CHAR,
+        }
+    }
+}
+
+impl BamUnpack for LUTBamUnpacker {
+    fn unpack_bytes(&self, seq: &[u8], out: &mut [u8]) {
+        assert!(out.len() >= seq.len() * 2);
+
+        for (byte, out) in seq.iter().zip(out.chunks_exact_mut(2)) {
+            out[0] = self.lut[(byte >> 4) as usize];
+            out[1] = self.lut[(byte & 0b1111) as usize];
+        }
+    }
+}
+
+/// Converts between BAM codes and nibble masks, swapping the nibbles of every byte and mapping
+/// each nibble through `lut`.
+///
+/// The default `lut` is the identity, which converts both ways for the default masks. A custom
+/// alphabet needs a table from BAM codes to its masks, or the other way around.
+pub struct LUTBamNibbleConverter {
+    lut: [u8; 16],
+}
+
+impl Default for LUTBamNibbleConverter {
+    fn default() -> Self {
+        Self {
+            lut: core::array::from_fn(|i| i as u8),
+        }
+    }
+}
+
+impl LUTBamNibbleConverter {
+    pub const fn new(lut: [u8; 16]) -> Self {
+        Self { lut }
+    }
+
+    fn convert_byte(&self, byte: u8) -> u8 {
+        self.lut[(byte & 0b1111) as usize] << 4 | self.lut[(byte >> 4) as usize]
+    }
+}
+
+impl CodeConverter for LUTBamNibbleConverter {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        assert!(out.len() >= code.len());
+
+        for (byte, out) in code.iter().zip(out) {
+            *out = self.convert_byte(*byte);
+        }
+    }
+}
+
+impl CodeConverterInPlace for LUTBamNibbleConverter {
+    fn convert_in_place<'a>(&self, code: &'a mut [u8]) -> &'a mut [u8] {
+        for byte in code.iter_mut() {
+            *byte = self.convert_byte(*byte);
+        }
+        code
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(x86_ssse3, "ssse3");
+
+/// The SSSE3 BAM packer, falls back to [`LUTBamPacker`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[repr(align(16))]
+pub struct SSSE3BamPacker {
+    lut: [u8; 32],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for SSSE3BamPacker {
+    fn default() -> Self {
+        Self {
+            lut: core::array::from_fn(|i| BAM_CHAR_TO_CODE[b'A' as usize + i]),
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl SSSE3BamPacker {
+    #[target_feature(enable = "ssse3")]
+    fn pack_impl(&self, code: &[u8], out: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        assert!(out.len() >= code.len().div_ceil(2));
+
+        let simd_len = code.len() / 16;
+        let (code_simd, code_rest) = code.split_at(simd_len * 16);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 8);
+
+        unsafe {
+            let lut0 = _mm_load_si128(self.lut.as_ptr().cast());
+            let lut1 = _mm_load_si128(self.lut.as_ptr().add(16).cast());
+            let (to_upper, offset, sixteen) = (
+                _mm_set1_epi8(!0x20),
+                _mm_set1_epi8(b'A' as _),
+                _mm_set1_epi8(16),
+            );
+            let (fifteen, equals) = (_mm_set1_epi8(15), _mm_set1_epi8(b'=' as _));
+            for (chunk, out_chunk) in code_simd.chunks_exact(16).zip(out_simd.chunks_exact_mut(8)) {
+                let chunk = _mm_loadu_si128(chunk.as_ptr().cast());
+
+                // letters index the two tables, everything else is N except for '='
+                let index0 = _mm_sub_epi8(_mm_and_si128(chunk, to_upper), offset);
+                let index1 = _mm_sub_epi8(index0, sixteen);
+                let in0 = _mm_cmpeq_epi8(_mm_min_epu8(index0, fifteen), index0);
+                let in1 = _mm_cmpeq_epi8(_mm_min_epu8(index1, fifteen), index1);
+                let mut codes = _mm_or_si128(
+                    _mm_and_si128(in0, _mm_shuffle_epi8(lut0, index0)),
+                    _mm_and_si128(in1, _mm_shuffle_epi8(lut1, index1)),
+                );
+                codes = _mm_or_si128(codes, _mm_andnot_si128(_mm_or_si128(in0, in1), fifteen));
+                codes = _mm_andnot_si128(_mm_cmpeq_epi8(chunk, equals), codes);
+
+                // the first base of each pair goes into the high nibble
+                let pairs = _mm_or_si128(_mm_slli_epi16(codes, 4), _mm_srli_epi16(codes, 8));
+                let pairs = _mm_and_si128(pairs, _mm_set1_epi16(0xff));
+                _mm_storel_epi64(
+                    out_chunk.as_mut_ptr().cast(),
+                    _mm_packus_epi16(pairs, pairs),
+                );
+            }
+        }
+
+        LUTBamPacker::default().convert(code_rest, out_rest);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl CodeConverter for SSSE3BamPacker {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        if x86_ssse3::get() {
+            unsafe { self.pack_impl(code, out) }
+        } else {
+            LUTBamPacker::default().convert(code, out)
+        }
+    }
+}
+
+/// The SSSE3 BAM unpacker, falls back to [`LUTBamUnpacker`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[repr(align(16))]
+pub struct SSSE3BamUnpacker {
+    lut: [u8; 16],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for SSSE3BamUnpacker {
+    fn default() -> Self {
+        Self {
+            lut: BAM_CODE_TO_CHAR,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl SSSE3BamUnpacker {
+    #[target_feature(enable = "ssse3")]
+    fn unpack_impl(&self, seq: &[u8], out: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        assert!(out.len() >= seq.len() * 2);
+
+        let simd_len = seq.len() / 8;
+        let (seq_simd, seq_rest) = seq.split_at(simd_len * 8);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+        unsafe {
+            let lut = _mm_load_si128(self.lut.as_ptr().cast());
+            let nibble = _mm_set1_epi8(0b1111);
+            for (chunk, out_chunk) in seq_simd.chunks_exact(8).zip(out_simd.chunks_exact_mut(16)) {
+                let lows = _mm_loadl_epi64(chunk.as_ptr().cast());
+                let highs = _mm_srli_epi16(lows, 4);
+                // high nibble first
+                let codes = _mm_and_si128(_mm_unpacklo_epi8(highs, lows), nibble);
+                _mm_storeu_si128(out_chunk.as_mut_ptr().cast(), _mm_shuffle_epi8(lut, codes));
+            }
+        }
+
+        LUTBamUnpacker { lut: self.lut }.unpack_bytes(seq_rest, out_rest);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl BamUnpack for SSSE3BamUnpacker {
+    fn unpack_bytes(&self, seq: &[u8], out: &mut [u8]) {
+        if x86_ssse3::get() {
+            unsafe { self.unpack_impl(seq, out) }
+        } else {
+            LUTBamUnpacker { lut: self.lut }.unpack_bytes(seq, out)
+        }
+    }
+}
+
+/// The SSSE3 BAM nibble converter, falls back to [`LUTBamNibbleConverter`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[repr(align(16))]
+pub struct SSSE3BamNibbleConverter {
+    lut: [u8; 16],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for SSSE3BamNibbleConverter {
+    fn default() -> Self {
+        Self {
+            lut: core::array::from_fn(|i| i as u8),
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl SSSE3BamNibbleConverter {
+    pub const fn new(lut: [u8; 16]) -> Self {
+        Self { lut }
+    }
+
+    /// Convert `len` bytes from `code` to `out`, which may be the same pointer.
+    #[target_feature(enable = "ssse3")]
+    unsafe fn convert_impl(&self, code: *const u8, out: *mut u8, len: usize) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        let simd_len = len / 16;
+        unsafe {
+            let low = _mm_load_si128(self.lut.as_ptr().cast());
+            let high = _mm_slli_epi16(low, 4);
+            let nibble = _mm_set1_epi8(0b1111);
+            for i in 0..simd_len {
+                let v = _mm_loadu_si128(code.add(i * 16).cast());
+                let to_high = _mm_shuffle_epi8(high, _mm_and_si128(v, nibble));
+                let to_low = _mm_shuffle_epi8(low, _mm_and_si128(_mm_srli_epi16(v, 4), nibble));
+                _mm_storeu_si128(out.add(i * 16).cast(), _mm_or_si128(to_high, to_low));
+            }
+
+            let scalar = LUTBamNibbleConverter::new(self.lut);
+            for i in simd_len * 16..len {
+                *out.add(i) = scalar.convert_byte(*code.add(i));
+            }
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl CodeConverter for SSSE3BamNibbleConverter {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        assert!(out.len() >= code.len());
+
+        if x86_ssse3::get() {
+            unsafe { self.convert_impl(code.as_ptr(), out.as_mut_ptr(), code.len()) }
+        } else {
+            LUTBamNibbleConverter::new(self.lut).convert(code, out)
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl CodeConverterInPlace for SSSE3BamNibbleConverter {
+    fn convert_in_place<'a>(&self, code: &'a mut [u8]) -> &'a mut [u8] {
+        if x86_ssse3::get() {
+            let ptr = code.as_mut_ptr();
+            unsafe { self.convert_impl(ptr, ptr, code.len()) };
+            code
+        } else {
+            LUTBamNibbleConverter::new(self.lut).convert_in_place(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_alphabet::{LUTPacker, LUTUnpacker, Unpack};
+    use crate::packed::PackedSlice;
+    use rand::{Rng, SeedableRng};
+
+    const LENGTHS: [usize; 10] = [0, 1, 2, 15, 16, 17, 31, 32, 33, 1001];
+
+    fn random_seq(rng: &mut impl Rng, n: usize, alphabet: &[u8]) -> [u8; 1024] {
+        let mut seq = [0; 1024];
+        for c in &mut seq[..n] {
+            *c = alphabet[rng.random_range(0..alphabet.len())];
+        }
+        seq
+    }
+
+    fn test_bam<P: CodeConverter, U: BamUnpack, C: CodeConverter + CodeConverterInPlace>(
+        packer: &P,
+        unpacker: &U,
+        converter: &C,
+    ) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for n in LENGTHS {
+            // every byte packs like the scalar packer
+            let mut bytes = [0; 1024];
+            bytes[..n].iter_mut().for_each(|b| *b = rng.random());
+            let (mut expected, mut out) = ([0; 512], [0; 512]);
+            LUTBamPacker::default().convert(&bytes[..n], &mut expected);
+            packer.convert(&bytes[..n], &mut out);
+            assert_eq!(out, expected, "pack mismatch for length {}", n);
+
+            let seq = random_seq(&mut rng, n, b"=ACMGRSVTWYHKDBNacmgrsvtwyhkdbn");
+            let packed = &mut out[..n.div_ceil(2)];
+            packer.convert(&seq[..n], packed);
+            let mut unpacked = [0; 1024];
+            unpacker.unpack(packed, n, &mut unpacked);
+            assert_eq!(unpacked[..n], seq[..n].to_ascii_uppercase(), "length {}", n);
+
+            // BAM nibbles become the same masks as the custom_alphabet packers write
+            let seq = random_seq(&mut rng, n, b"ACMGRSVTWYHKDBNacmgrsvtwyhkdbnUu");
+            let mut bam = [0; 512];
+            packer.convert(&seq[..n], &mut bam);
+            let mut masks = [0; 512];
+            let (bam, masks) = (&bam[..n.div_ceil(2)], &mut masks[..n.div_ceil(2)]);
+            converter.convert(bam, masks);
+            let mut expected = [0; 512];
+            LUTPacker::default().convert(&seq[..n], &mut expected);
+            assert_eq!(masks, &expected[..n.div_ceil(2)], "length {}", n);
+
+            let mut unpacked = [0; 1024];
+            LUTUnpacker::default().unpack(PackedSlice::new(masks, n), &mut unpacked);
+            let mut back = [0; 1024];
+            unpacker.unpack(bam, n, &mut back);
+            assert_eq!(unpacked[..n], back[..n]);
+
+            // and back again in place
+            assert_eq!(converter.convert_in_place(masks), bam);
+        }
+    }
+
+    #[test]
+    fn test_bam_layout() {
+        let mut out = [0; 3];
+        LUTBamPacker::default().convert(b"ACGTN", &mut out);
+        assert_eq!(out, [0x12, 0x48, 0xf0]);
+        let mut seq = [0; 5];
+        LUTBamUnpacker::default().unpack(&out, 5, &mut seq);
+        assert_eq!(&seq, b"ACGTN");
+    }
+
+    #[test]
+    fn test_lut_bam() {
+        test_bam(
+            &LUTBamPacker::default(),
+            &LUTBamUnpacker::default(),
+            &LUTBamNibbleConverter::default(),
+        );
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_ssse3_bam() {
+        test_bam(
+            &SSSE3BamPacker::default(),
+            &SSSE3BamUnpacker::default(),
+            &SSSE3BamNibbleConverter::default(),
+        );
+    }
+}
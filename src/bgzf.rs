@@ -0,0 +1,632 @@
+//! Gzip and BGZF input, behind the `gzip` feature.
+//!
+//! [`FastaReader::open`] and [`FastqReader::open`] read `.gz` and plain files alike, telling them
+//! apart by the gzip magic bytes. For other inputs, wrap the reader in [`MaybeGzReader`], which
+//! does the same detection and otherwise passes the input through. BGZF files are valid
+//! multi-member gzip and work there too.
+//!
+//! [`BgzfReader`] decompresses BGZF one block at a time into a buffer that it hands out through
+//! [`BufRead::fill_buf`], which is where the FASTA and FASTQ readers run the converters. It can
+//! seek to virtual offsets, and with a [`GziIndex`] to uncompressed offsets, which is what
+//! [`IndexedFastaReader`](crate::fai::IndexedFastaReader) needs for a bgzipped reference.
+//!
+//! ```no_run
+//! use nucleotide_converter::fasta::FastaReader;
+//!
+//! for record in FastaReader::open("genome.fa.gz")? {
+//!     let record = record?;
+//!     println!("{}\t{}", record.name().escape_ascii(), record.seq.len());
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use flate2::bufread::MultiGzDecoder;
+use flate2::{Crc, Decompress, FlushDecompress, Status};
+
+use crate::fasta::FastaReader;
+use crate::fastq::FastqReader;
+use crate::invalid_data;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The largest uncompressed size of a BGZF block.
+const MAX_BLOCK_SIZE: usize = 64 * 1024;
+
+enum Inner<R> {
+    Plain(R),
+    Gzip(BufReader<MultiGzDecoder<R>>),
+}
+
+/// Decompresses gzip input and passes anything else through unchanged.
+pub struct MaybeGzReader<R> {
+    inner: Inner<R>,
+}
+
+impl<R: BufRead> MaybeGzReader<R> {
+    /// Peek at the start of `reader` to see whether it is gzip.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let inner = if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+            Inner::Gzip(BufReader::with_capacity(
+                MAX_BLOCK_SIZE,
+                MultiGzDecoder::new(reader),
+            ))
+        } else {
+            Inner::Plain(reader)
+        };
+        Ok(Self { inner })
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.inner, Inner::Gzip(_))
+    }
+
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            Inner::Plain(reader) => reader,
+            Inner::Gzip(reader) => reader.into_inner().into_inner(),
+        }
+    }
+}
+
+impl<R: BufRead> Read for MaybeGzReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::Plain(reader) => reader.read(buf),
+            Inner::Gzip(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for MaybeGzReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match &mut self.inner {
+            Inner::Plain(reader) => reader.fill_buf(),
+            Inner::Gzip(reader) => reader.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match &mut self.inner {
+            Inner::Plain(reader) => reader.consume(amt),
+            Inner::Gzip(reader) => reader.consume(amt),
+        }
+    }
+}
+
+/// Open `path` and decompress it if it is gzip.
+fn open_maybe_gz(path: &Path) -> io::Result<MaybeGzReader<BufReader<File>>> {
+    MaybeGzReader::new(BufReader::new(File::open(path)?))
+}
+
+impl FastaReader<MaybeGzReader<BufReader<File>>> {
+    /// Open a plain or gzip-compressed FASTA file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(open_maybe_gz(path.as_ref())?))
+    }
+}
+
+impl FastqReader<MaybeGzReader<BufReader<File>>> {
+    /// Open a plain or gzip-compressed FASTQ file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(open_maybe_gz(path.as_ref())?))
+    }
+}
+
+/// Read into `buf` until it is full or the input ends, returning the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+/// Reads a BGZF file block by block.
+pub struct BgzfReader<R> {
+    reader: R,
+    decompress: Decompress,
+    compressed: Vec<u8>,
+    block: Vec<u8>,
+    pos: usize,
+    /// Compressed offset of the current and of the next block.
+    block_offset: u64,
+    next_offset: u64,
+    /// Uncompressed offset of the next block, unknown after seeking to a virtual offset that is
+    /// not in the index.
+    next_start: Option<u64>,
+    index: Option<GziIndex>,
+}
+
+impl<R: Read> BgzfReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decompress: Decompress::new(false),
+            compressed: Vec::new(),
+            block: Vec::with_capacity(MAX_BLOCK_SIZE),
+            pos: 0,
+            block_offset: 0,
+            next_offset: 0,
+            next_start: Some(0),
+            index: None,
+        }
+    }
+
+    /// A reader that can [`Seek`] to uncompressed offsets with `index`.
+    pub fn with_index(reader: R, index: GziIndex) -> Self {
+        Self {
+            index: Some(index),
+            ..Self::new(reader)
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The virtual offset of the next byte: the compressed offset of its block in the upper 48
+    /// bits and its offset within the uncompressed block in the lower 16.
+    pub fn virtual_offset(&self) -> u64 {
+        self.block_offset << 16 | self.pos as u64
+    }
+
+    /// Read and decompress the next block. Returns `false` at the end of the input.
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut header = [0; 12];
+        match read_full(&mut self.reader, &mut header)? {
+            0 => return Ok(false),
+            12 => {}
+            _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+        if header[..4] != [GZIP_MAGIC[0], GZIP_MAGIC[1], 8, 4] {
+            return Err(invalid_data("not a BGZF block"));
+        }
+
+        let extra_len = u16::from_le_bytes([header[10], header[11]]) as usize;
+        self.compressed.resize(extra_len, 0);
+        self.reader.read_exact(&mut self.compressed)?;
+        let mut block_size = None;
+        let mut extra = &self.compressed[..];
+        while extra.len() >= 4 {
+            let field_len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+            if extra[..2] == *b"BC" && field_len == 2 && extra.len() >= 6 {
+                block_size = Some(u16::from_le_bytes([extra[4], extra[5]]) as usize + 1);
+            }
+            extra = extra.get(4 + field_len..).unwrap_or_default();
+        }
+        let block_size = block_size.ok_or_else(|| invalid_data("missing BGZF block size"))?;
+        let Some(data_len) = block_size.checked_sub(12 + extra_len + 8) else {
+            return Err(invalid_data("invalid BGZF block size"));
+        };
+
+        self.compressed.resize(data_len + 8, 0);
+        self.reader.read_exact(&mut self.compressed)?;
+        let (data, footer) = self.compressed.split_at(data_len);
+        let crc = u32::from_le_bytes(footer[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(footer[4..].try_into().unwrap()) as usize;
+        if len > MAX_BLOCK_SIZE {
+            return Err(invalid_data("BGZF block larger than 64 KiB"));
+        }
+
+        self.block.resize(len, 0);
+        self.decompress.reset(false);
+        let status = self
+            .decompress
+            .decompress(data, &mut self.block, FlushDecompress::Finish)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if status != Status::StreamEnd || self.decompress.total_out() != len as u64 {
+            return Err(invalid_data("BGZF block size does not match its data"));
+        }
+        let mut check = Crc::new();
+        check.update(&self.block);
+        if check.sum() != crc {
+            return Err(invalid_data("BGZF block CRC mismatch"));
+        }
+
+        self.pos = 0;
+        self.block_offset = self.next_offset;
+        self.next_offset += block_size as u64;
+        self.next_start = self.next_start.map(|start| start + len as u64);
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Continue reading at the virtual offset `offset`, see [`virtual_offset`](Self::virtual_offset).
+    pub fn seek_virtual(&mut self, offset: u64) -> io::Result<()> {
+        let (block_offset, within) = (offset >> 16, (offset & 0xffff) as usize);
+        let start = self.index.as_ref().and_then(|index| {
+            let i = index.blocks.binary_search_by_key(&block_offset, |b| b.0);
+            i.ok().map(|i| index.blocks[i].1)
+        });
+        self.load_block_at(block_offset, start)?;
+        if within > self.block.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "virtual offset past the end of its block",
+            ));
+        }
+        self.pos = within;
+        Ok(())
+    }
+
+    /// Load the block at compressed offset `offset`, whose data starts at uncompressed offset
+    /// `start` if known.
+    fn load_block_at(&mut self, offset: u64, start: Option<u64>) -> io::Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.next_offset = offset;
+        self.next_start = start;
+        self.block.clear();
+        self.pos = 0;
+        if !self.read_block()? {
+            self.block_offset = offset;
+        }
+        Ok(())
+    }
+
+    /// The uncompressed offset of the next byte.
+    fn position(&self) -> io::Result<u64> {
+        let start = self.next_start.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "uncompressed position unknown after seeking to a virtual offset",
+            )
+        })?;
+        Ok(start - (self.block.len() - self.pos) as u64)
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.block.len() {
+            if !self.read_block()? {
+                break;
+            }
+        }
+        Ok(&self.block[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.block.len());
+    }
+}
+
+/// Seeks to uncompressed offsets. [`SeekFrom::Start`] needs the [`GziIndex`] given to
+/// [`with_index`](BgzfReader::with_index), [`SeekFrom::End`] is not supported.
+impl<R: Read + Seek> Seek for BgzfReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(target) => target,
+            SeekFrom::Current(delta) => self
+                .position()?
+                .checked_add_signed(delta)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "cannot seek from the end of a BGZF file",
+                ));
+            }
+        };
+        let Some(index) = &self.index else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "seeking in a BGZF file needs a .gzi index",
+            ));
+        };
+
+        let (offset, start) = index.block(target);
+        self.load_block_at(offset, Some(start))?;
+        // the target may lie past empty blocks or at the very end
+        while target - self.position()? > (self.block.len() - self.pos) as u64 {
+            if !self.read_block()? {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "seek past the end of the BGZF file",
+                ));
+            }
+        }
+        self.pos += (target - self.position()?) as usize;
+        Ok(target)
+    }
+}
+
+/// A `.gzi` index: the compressed and uncompressed offsets of every BGZF block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GziIndex {
+    /// Sorted (compressed, uncompressed) offsets, starting with the first block at (0, 0).
+    blocks: Vec<(u64, u64)>,
+}
+
+impl Default for GziIndex {
+    fn default() -> Self {
+        Self {
+            blocks: vec![(0, 0)],
+        }
+    }
+}
+
+impl GziIndex {
+    /// Index a BGZF file by reading through all of its blocks.
+    pub fn build<R: Read>(reader: R) -> io::Result<Self> {
+        let mut index = Self::default();
+        let mut reader = BgzfReader::new(reader);
+        while reader.read_block()? {
+            let start = reader.next_start.unwrap();
+            if !reader.block.is_empty() && reader.block_offset != 0 {
+                index
+                    .blocks
+                    .push((reader.block_offset, start - reader.block.len() as u64));
+            }
+        }
+        Ok(index)
+    }
+
+    /// Read a `.gzi` file: a little-endian count, then the pairs of offsets of every block but
+    /// the first.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut word = [0; 8];
+        reader.read_exact(&mut word)?;
+        let count = u64::from_le_bytes(word);
+        let mut index = Self::default();
+        for _ in 0..count {
+            reader.read_exact(&mut word)?;
+            let compressed = u64::from_le_bytes(word);
+            reader.read_exact(&mut word)?;
+            let uncompressed = u64::from_le_bytes(word);
+            if index.blocks.last().unwrap() >= &(compressed, uncompressed) {
+                return Err(invalid_data(".gzi offsets are not sorted"));
+            }
+            index.blocks.push((compressed, uncompressed));
+        }
+        Ok(index)
+    }
+
+    /// Write the index in the `.gzi` format.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.blocks.len() as u64 - 1).to_le_bytes())?;
+        for (compressed, uncompressed) in &self.blocks[1..] {
+            writer.write_all(&compressed.to_le_bytes())?;
+            writer.write_all(&uncompressed.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// The compressed and uncompressed offsets of the block holding uncompressed offset `pos`.
+    pub fn block(&self, pos: u64) -> (u64, u64) {
+        let i = self.blocks.partition_point(|block| block.1 <= pos);
+        self.blocks[i - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NaiveCodeConverter;
+    use crate::fai::{FaiIndex, IndexedFastaReader};
+    use crate::fastq::ReadArena;
+    use flate2::Compression;
+    use flate2::write::{DeflateEncoder, GzEncoder};
+    use std::io::Cursor;
+
+    const EOF_BLOCK: [u8; 28] = [
+        0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0, 0x1b, 0, 3, 0, 0, 0, 0, 0,
+        0, 0, 0, 0,
+    ];
+
+    /// Compress `data` into BGZF blocks of `block_len` bytes, followed by the EOF block.
+    fn bgzf(data: &[u8], block_len: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        for chunk in data.chunks(block_len) {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(chunk).unwrap();
+            let deflated = encoder.finish().unwrap();
+            let mut crc = Crc::new();
+            crc.update(chunk);
+
+            out.extend_from_slice(&[
+                0x1f, 0x8b, 8, 4, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0,
+            ]);
+            out.extend_from_slice(&((deflated.len() + 25) as u16).to_le_bytes());
+            out.extend_from_slice(&deflated);
+            out.extend_from_slice(&crc.sum().to_le_bytes());
+            out.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        }
+        out.extend_from_slice(&EOF_BLOCK);
+        out
+    }
+
+    fn fasta() -> Vec<u8> {
+        let mut fasta = Vec::new();
+        for i in 0..20 {
+            writeln!(fasta, ">seq{i}").unwrap();
+            for j in 0..i * 7 {
+                fasta.push(b"ACGTN"[(i + j) % 5]);
+                if j % 60 == 59 {
+                    fasta.push(b'\n');
+                }
+            }
+            fasta.push(b'\n');
+        }
+        fasta
+    }
+
+    fn records<R: BufRead>(reader: R) -> Vec<(Vec<u8>, Vec<u8>)> {
+        FastaReader::new(reader)
+            .map(|record| {
+                let record = record.unwrap();
+                (record.header, record.seq)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_maybe_gz() {
+        let fasta = fasta();
+        let expected = records(&fasta[..]);
+
+        let plain = MaybeGzReader::new(&fasta[..]).unwrap();
+        assert!(!plain.is_compressed());
+        assert_eq!(records(plain), expected);
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&fasta).unwrap();
+        let gz = gz.finish().unwrap();
+        let reader = MaybeGzReader::new(&gz[..]).unwrap();
+        assert!(reader.is_compressed());
+        assert_eq!(records(reader), expected);
+
+        let bgzf = bgzf(&fasta, 100);
+        assert_eq!(records(MaybeGzReader::new(&bgzf[..]).unwrap()), expected);
+        assert_eq!(records(BgzfReader::new(&bgzf[..])), expected);
+
+        let fastq = b"@r1\nACGT\n+\nIIII\n@r2\nGG\n+\n#I\n";
+        let mut arena = ReadArena::default();
+        let compressed = self::bgzf(fastq, 10);
+        let mut reader = FastqReader::new(MaybeGzReader::new(&compressed[..]).unwrap());
+        let converter = NaiveCodeConverter::default();
+        while reader.read_converted(&converter, &mut arena).unwrap() {}
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(1).unwrap().seq, [3, 3]);
+    }
+
+    #[test]
+    fn test_open() {
+        let fasta = fasta();
+        let expected = records(&fasta[..]);
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&fasta).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("bgzf-test-open-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (plain, compressed) = (dir.join("genome.fa"), dir.join("genome.fa.gz"));
+        std::fs::write(&plain, &fasta).unwrap();
+        std::fs::write(&compressed, gz.finish().unwrap()).unwrap();
+        let fastq = dir.join("reads.fq.gz");
+        std::fs::write(&fastq, bgzf(b"@r1\nACGT\n+\nIIII\n", 10)).unwrap();
+
+        for (path, is_compressed) in [(&plain, false), (&compressed, true)] {
+            let reader = FastaReader::open(path).unwrap();
+            let records: Vec<_> = reader
+                .map(|record| {
+                    let record = record.unwrap();
+                    (record.header, record.seq)
+                })
+                .collect();
+            assert_eq!(records, expected);
+            let reader = FastaReader::open(path).unwrap();
+            assert_eq!(reader.into_inner().is_compressed(), is_compressed);
+        }
+
+        let mut arena = ReadArena::default();
+        let mut reader = FastqReader::open(&fastq).unwrap();
+        while reader
+            .read_converted(&NaiveCodeConverter::default(), &mut arena)
+            .unwrap()
+        {}
+        assert_eq!(arena.get(0).unwrap().seq, [0, 2, 3, 1]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_virtual_offsets() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let compressed = bgzf(&data, 300);
+        let mut reader = BgzfReader::new(Cursor::new(compressed));
+
+        let mut offsets = Vec::new();
+        let mut buf = [0; 70];
+        let mut pos = 0;
+        while pos < data.len() {
+            reader.fill_buf().unwrap();
+            offsets.push((pos, reader.virtual_offset()));
+            let n = reader.read(&mut buf).unwrap();
+            assert_eq!(buf[..n], data[pos..pos + n]);
+            pos += n;
+        }
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+
+        for &(pos, offset) in offsets.iter().rev() {
+            reader.seek_virtual(offset).unwrap();
+            assert_eq!(reader.virtual_offset(), offset);
+            assert_eq!(reader.fill_buf().unwrap()[0], data[pos]);
+        }
+        assert!(reader.seek(SeekFrom::Start(0)).is_err());
+    }
+
+    #[test]
+    fn test_gzi() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        let compressed = bgzf(&data, 300);
+        let index = GziIndex::build(&compressed[..]).unwrap();
+        assert_eq!(
+            index.blocks.iter().map(|b| b.1).collect::<Vec<_>>(),
+            [0, 300, 600, 900]
+        );
+
+        let mut gzi = Vec::new();
+        index.write(&mut gzi).unwrap();
+        assert_eq!(gzi.len(), 8 + 3 * 16);
+        assert_eq!(gzi[..8], 3u64.to_le_bytes());
+        assert_eq!(GziIndex::read(&gzi[..]).unwrap(), index);
+
+        let mut reader = BgzfReader::with_index(Cursor::new(compressed), index);
+        for pos in [999, 0, 300, 299, 650, 1000] {
+            assert_eq!(reader.seek(SeekFrom::Start(pos)).unwrap(), pos);
+            assert_eq!(reader.stream_position().unwrap(), pos);
+            let mut byte = [0; 1];
+            let n = reader.read(&mut byte).unwrap();
+            assert_eq!(
+                byte[..n],
+                data[pos as usize..(pos as usize + 1).min(data.len())]
+            );
+        }
+        assert_eq!(reader.seek(SeekFrom::Current(-501)).unwrap(), 499);
+        assert!(reader.seek(SeekFrom::Start(1001)).is_err());
+    }
+
+    #[test]
+    fn test_fetch_bgzf_fasta() {
+        let fasta = fasta();
+        let compressed = bgzf(&fasta, 256);
+        let gzi = GziIndex::build(&compressed[..]).unwrap();
+        let fai = FaiIndex::build(MaybeGzReader::new(&compressed[..]).unwrap()).unwrap();
+        assert_eq!(
+            fai.entries(),
+            FaiIndex::build(&fasta[..]).unwrap().entries()
+        );
+
+        let mut plain = IndexedFastaReader::new(Cursor::new(&fasta), fai.clone());
+        let mut gz =
+            IndexedFastaReader::new(BgzfReader::with_index(Cursor::new(compressed), gzi), fai);
+        let converter = NaiveCodeConverter::default();
+        let (mut expected, mut out) = ([0; 200], [0; 200]);
+        for region in [&b"seq19:50-133"[..], b"seq3", b"seq10:60-61", b"seq1:1-1"] {
+            let len = plain
+                .fetch_region(&converter, region, &mut expected)
+                .unwrap();
+            assert_eq!(gz.fetch_region(&converter, region, &mut out).unwrap(), len);
+            assert_eq!(out[..len], expected[..len]);
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! Samtools-style `.fai` indexes and region fetching.
+//!
+//! A `.fai` line gives the name, length, byte offset of the first base, bases per line and bytes
+//! per line of one FASTA record. Together they locate any base of the file, so
+//! [`IndexedFastaReader`] reads just the lines of a region and hands the bases, with the line
+//! endings stripped, to a [`CodeConverter`] or a packer writing into the caller's buffer. The
+//! buffers are reused between fetches.
+
+use core::ops::Range;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use crate::custom_alphabet::Pack;
+use crate::fasta::strip_newlines;
+use crate::packed::PackedSlice;
+use crate::{CodeConverter, invalid_data};
+
+/// One line of a `.fai` index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaiEntry {
+    /// The header up to the first whitespace.
+    pub name: Vec<u8>,
+    /// Number of bases.
+    pub len: u64,
+    /// Byte offset of the first base in the FASTA file.
+    pub offset: u64,
+    /// Bases on every line but the last.
+    pub line_bases: u64,
+    /// Bytes of every line but the last, including the line ending.
+    pub line_width: u64,
+}
+
+impl FaiEntry {
+    /// Byte offset of base `pos` in the FASTA file.
+    fn base_offset(&self, pos: u64) -> u64 {
+        self.offset + pos / self.line_bases * self.line_width + pos % self.line_bases
+    }
+}
+
+/// A `.fai` index.
+#[derive(Debug, Clone, Default)]
+pub struct FaiIndex {
+    entries: Vec<FaiEntry>,
+    index: HashMap<Vec<u8>, usize>,
+}
+
+impl FaiIndex {
+    fn push(&mut self, entry: FaiEntry) -> io::Result<()> {
+        if self.index.contains_key(&entry.name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("duplicate sequence name '{}'", entry.name.escape_ascii()),
+            ));
+        }
+        self.index.insert(entry.name.clone(), self.entries.len());
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Index a FASTA file. Every line of a record but the last must have the same length.
+    pub fn build<R: BufRead>(mut reader: R) -> io::Result<Self> {
+        let mut index = Self::default();
+        let mut current: Option<FaiEntry> = None;
+        // a line shorter than the first one ends the record
+        let mut ended = false;
+        let mut offset = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let width = reader.read_until(b'\n', &mut line)? as u64;
+            if width == 0 {
+                break;
+            }
+            offset += width;
+            let bases = line
+                .iter()
+                .rposition(|&b| b != b'\n' && b != b'\r')
+                .map_or(0, |i| i + 1) as u64;
+
+            if line[0] == b'>' {
+                if let Some(entry) = current.take() {
+                    index.push(entry)?;
+                }
+                let name_len = line[1..bases as usize]
+                    .iter()
+                    .position(u8::is_ascii_whitespace)
+                    .unwrap_or(bases as usize - 1);
+                current = Some(FaiEntry {
+                    name: line[1..1 + name_len].to_vec(),
+                    len: 0,
+                    offset,
+                    line_bases: 0,
+                    line_width: 0,
+                });
+                ended = false;
+                continue;
+            }
+
+            let Some(entry) = current.as_mut() else {
+                if bases == 0 {
+                    continue;
+                }
+                return Err(invalid_data("FASTA sequence before the first header"));
+            };
+            if bases == 0 {
+                ended = true;
+                continue;
+            }
+            if ended || (entry.line_bases > 0 && bases > entry.line_bases) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "different line lengths in '{}', the file cannot be indexed",
+                        entry.name.escape_ascii()
+                    ),
+                ));
+            }
+            if entry.line_bases == 0 {
+                entry.line_bases = bases;
+                entry.line_width = width;
+            } else if bases < entry.line_bases || width != entry.line_width {
+                ended = true;
+            }
+            entry.len += bases;
+        }
+        if let Some(entry) = current {
+            index.push(entry)?;
+        }
+        Ok(index)
+    }
+
+    /// Read a `.fai` file.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut index = Self::default();
+        for line in reader.split(b'\n') {
+            let line = line?;
+            let line = line.strip_suffix(b"\r").unwrap_or(&line);
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split(|&b| b == b'\t');
+            let name = fields.next().unwrap_or_default().to_vec();
+            let mut numbers = [0; 4];
+            for number in &mut numbers {
+                *number = fields
+                    .next()
+                    .and_then(|field| std::str::from_utf8(field).ok()?.parse().ok())
+                    .ok_or_else(|| invalid_data("invalid .fai line"))?;
+            }
+            let [len, offset, line_bases, line_width] = numbers;
+            if len > 0 && (line_bases == 0 || line_width < line_bases) {
+                return Err(invalid_data("invalid .fai line length"));
+            }
+            index.push(FaiEntry {
+                name,
+                len,
+                offset,
+                line_bases,
+                line_width,
+            })?;
+        }
+        Ok(index)
+    }
+
+    /// Write the index in the `.fai` format.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writer.write_all(&entry.name)?;
+            writeln!(
+                writer,
+                "\t{}\t{}\t{}\t{}",
+                entry.len, entry.offset, entry.line_bases, entry.line_width
+            )?;
+        }
+        writer.flush()
+    }
+
+    /// The entries in file order.
+    pub fn entries(&self) -> &[FaiEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, name: &[u8]) -> Option<&FaiEntry> {
+        Some(&self.entries[*self.index.get(name)?])
+    }
+
+    fn entry(&self, name: &[u8]) -> io::Result<&FaiEntry> {
+        self.get(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("sequence '{}' not in .fai index", name.escape_ascii()),
+            )
+        })
+    }
+
+    /// Parse a samtools region, `name`, `name:start` or `name:start-end`, 1-based and inclusive,
+    /// with optional thousands separators. Returns the name and the 0-based range, with the end
+    /// clamped to the sequence length.
+    ///
+    /// A name that contains `:` is matched whole first, as samtools does.
+    pub fn parse_region<'a>(&self, region: &'a [u8]) -> io::Result<(&'a [u8], Range<usize>)> {
+        if let Some(entry) = self.get(region) {
+            return Ok((region, 0..entry.len as usize));
+        }
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid region '{}'", region.escape_ascii()),
+            )
+        };
+        let Some(colon) = region.iter().rposition(|&b| b == b':') else {
+            return Err(self.entry(region).unwrap_err());
+        };
+        let (name, coordinates) = (&region[..colon], &region[colon + 1..]);
+        let len = self.entry(name)?.len as usize;
+        let number = |field: &[u8]| -> Option<usize> {
+            let mut n: usize = 0;
+            for &b in field.iter().filter(|&&b| b != b',') {
+                if !b.is_ascii_digit() {
+                    return None;
+                }
+                n = n.checked_mul(10)?.checked_add((b - b'0') as usize)?;
+            }
+            (!field.is_empty()).then_some(n)
+        };
+        let (start, end) = match coordinates.iter().position(|&b| b == b'-') {
+            Some(dash) => (
+                number(&coordinates[..dash]).ok_or_else(invalid)?,
+                number(&coordinates[dash + 1..]).ok_or_else(invalid)?,
+            ),
+            None => (number(coordinates).ok_or_else(invalid)?, len),
+        };
+        let (start, end) = (start.max(1) - 1, end.min(len));
+        if start > end {
+            return Err(invalid());
+        }
+        Ok((name, start..end))
+    }
+}
+
+/// Fetches regions of an indexed FASTA file.
+pub struct IndexedFastaReader<R> {
+    reader: R,
+    index: FaiIndex,
+    raw: Vec<u8>,
+    seq: Vec<u8>,
+}
+
+impl<R: Read + Seek> IndexedFastaReader<R> {
+    pub fn new(reader: R, index: FaiIndex) -> Self {
+        Self {
+            reader,
+            index,
+            raw: Vec::new(),
+            seq: Vec::new(),
+        }
+    }
+
+    pub fn index(&self) -> &FaiIndex {
+        &self.index
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the bases of `name` in `range` into `self.seq`.
+    fn read_bases(&mut self, name: &[u8], range: &Range<usize>) -> io::Result<()> {
+        let entry = self.index.entry(name)?;
+        if range.start > range.end || range.end as u64 > entry.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "region {}..{} out of bounds for '{}' of length {}",
+                    range.start,
+                    range.end,
+                    name.escape_ascii(),
+                    entry.len
+                ),
+            ));
+        }
+
+        self.seq.clear();
+        if range.is_empty() {
+            return Ok(());
+        }
+        let start = entry.base_offset(range.start as u64);
+        let end = entry.base_offset(range.end as u64 - 1) + 1;
+        self.raw.resize((end - start) as usize, 0);
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut self.raw)?;
+        strip_newlines(&self.raw, &mut self.seq);
+        if self.seq.len() != range.len() {
+            return Err(invalid_data("FASTA file does not match its .fai index"));
+        }
+        Ok(())
+    }
+
+    /// Convert the bases of `name` in `range` (0-based) into `out`, which must be large enough
+    /// for `converter`.
+    pub fn fetch<C: CodeConverter>(
+        &mut self,
+        converter: &C,
+        name: &[u8],
+        range: Range<usize>,
+        out: &mut [u8],
+    ) -> io::Result<()> {
+        self.read_bases(name, &range)?;
+        converter.convert(&self.seq, out);
+        Ok(())
+    }
+
+    /// Pack the bases of `name` in `range` (0-based) into the start of `out`.
+    pub fn fetch_packed<'a, P: Pack>(
+        &mut self,
+        packer: &P,
+        name: &[u8],
+        range: Range<usize>,
+        out: &'a mut [u8],
+    ) -> io::Result<PackedSlice<'a>> {
+        self.read_bases(name, &range)?;
+        Ok(packer.pack_into(&self.seq, out))
+    }
+
+    /// [`fetch`](Self::fetch) a samtools region string, see [`FaiIndex::parse_region`].
+    /// Returns the number of bases.
+    pub fn fetch_region<C: CodeConverter>(
+        &mut self,
+        converter: &C,
+        region: &[u8],
+        out: &mut [u8],
+    ) -> io::Result<usize> {
+        let (name, range) = self.index.parse_region(region)?;
+        let len = range.len();
+        self.fetch(converter, name, range, out)?;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NaiveCodeConverter;
+    use crate::custom_alphabet::{LUTPacker, LUTUnpacker, Unpack};
+    use crate::fasta::FastaWriter;
+    use rand::{Rng, SeedableRng};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_build_fai() {
+        let fasta = b">chr1 first\nACGT\nACGT\nAC\n>chr2\r\nAAA\r\nCC\r\n>empty\n>chr3\nACGTA";
+        let index = FaiIndex::build(&fasta[..]).unwrap();
+        let mut fai = Vec::new();
+        index.write(&mut fai).unwrap();
+        assert_eq!(
+            fai,
+            b"chr1\t10\t12\t4\t5\nchr2\t5\t32\t3\t5\nempty\t0\t48\t0\t0\nchr3\t5\t54\t5\t5\n"
+        );
+        let read = FaiIndex::read(&fai[..]).unwrap();
+        assert_eq!(read.entries(), index.entries());
+
+        assert!(FaiIndex::build(&b">a\nACG\nA\nACG\n"[..]).is_err());
+        assert!(FaiIndex::build(&b">a\nACG\nACGT\n"[..]).is_err());
+        assert!(FaiIndex::build(&b">a\nACG\n\nACG\n"[..]).is_err());
+        assert!(FaiIndex::build(&b">a\nA\n>a\nA\n"[..]).is_err());
+        assert!(FaiIndex::read(&b"a\t1\t2\t3\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_parse_region() {
+        let index = FaiIndex::build(&b">chr1\nACGTACGTAC\n>HLA:1\nAC\n"[..]).unwrap();
+        let region = |r: &'static [u8]| index.parse_region(r).map(|(n, r)| (n.to_vec(), r));
+        assert_eq!(region(b"chr1").unwrap(), (b"chr1".to_vec(), 0..10));
+        assert_eq!(region(b"chr1:3").unwrap(), (b"chr1".to_vec(), 2..10));
+        assert_eq!(region(b"chr1:3-5").unwrap(), (b"chr1".to_vec(), 2..5));
+        assert_eq!(
+            region(b"chr1:1,0-2,000").unwrap(),
+            (b"chr1".to_vec(), 9..10)
+        );
+        assert_eq!(region(b"HLA:1").unwrap(), (b"HLA:1".to_vec(), 0..2));
+        assert_eq!(region(b"HLA:1:2").unwrap(), (b"HLA:1".to_vec(), 1..2));
+        assert_eq!(
+            region(b"chr2:1-2").unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            region(b"chr1:5-3").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert!(region(b"chr1:a-3").is_err());
+    }
+
+    #[test]
+    fn test_fetch() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let mut seqs = Vec::new();
+        let mut fasta = Vec::new();
+        for (i, width) in [1, 7, 60, 0].into_iter().enumerate() {
+            let seq: Vec<u8> = (0..rng.random_range(1..500))
+                .map(|_| b"ACGTNacgt"[rng.random_range(0..9)])
+                .collect();
+            let mut writer = FastaWriter::with_line_width(&mut fasta, width);
+            writer
+                .write_record(format!("seq{i} description").as_bytes(), &seq)
+                .unwrap();
+            seqs.push(seq);
+        }
+
+        let index = FaiIndex::build(&fasta[..]).unwrap();
+        let mut reader = IndexedFastaReader::new(Cursor::new(fasta), index);
+        let converter = NaiveCodeConverter::default();
+        let mut out = [0; 512];
+        for _ in 0..200 {
+            let i = rng.random_range(0..seqs.len());
+            let name = format!("seq{i}");
+            let start = rng.random_range(0..=seqs[i].len());
+            let end = rng.random_range(start..=seqs[i].len());
+
+            reader
+                .fetch(&converter, name.as_bytes(), start..end, &mut out)
+                .unwrap();
+            let mut expected = vec![0; end - start];
+            converter.convert(&seqs[i][start..end], &mut expected);
+            assert_eq!(out[..end - start], expected);
+
+            let packed = reader
+                .fetch_packed(&LUTPacker::default(), name.as_bytes(), start..end, &mut out)
+                .unwrap();
+            assert_eq!(
+                LUTUnpacker::default().unpack_to_vec(packed),
+                seqs[i][start..end].to_ascii_uppercase()
+            );
+
+            let region = format!("{name}:{}-{}", start + 1, end);
+            let len = reader
+                .fetch_region(&converter, region.as_bytes(), &mut out)
+                .unwrap();
+            assert_eq!(out[..len], expected);
+        }
+
+        let err = reader
+            .fetch(&converter, b"seq0", 0..10_000, &mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,829 @@
+//! Streaming FASTA reader and writer.
+//!
+//! Records are read one at a time from any [`BufRead`], with line endings stripped 16 bytes at a
+//! time, and handed to a [`CodeConverter`] or an in-place packer. Only the current record is held
+//! in memory.
+//!
+//! [`FastaWriter`] goes the other way, unpacking or converting each line straight into its place
+//! in the output buffer.
+
+use core::ops::Range;
+use std::io::{self, BufRead, Write};
+
+use crate::custom_alphabet::{PackInPlace, Unpack};
+use crate::packed::{PackedSequence, PackedSlice};
+use crate::{Align64, CodeConverter, invalid_data};
+
+/// A FASTA record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record<S> {
+    /// The header line without the leading `>` and the line ending.
+    pub header: Vec<u8>,
+    pub seq: S,
+}
+
+impl<S> Record<S> {
+    /// The header up to the first whitespace, the name used by `.fai` indexes and region strings.
+    pub fn name(&self) -> &[u8] {
+        let end = self
+            .header
+            .iter()
+            .position(u8::is_ascii_whitespace)
+            .unwrap_or(self.header.len());
+        &self.header[..end]
+    }
+}
+
+/// Reads FASTA records from a [`BufRead`].
+///
+/// Iterating yields the records with their raw sequence bytes, the `read_*` methods convert or
+/// pack the sequence while it is read.
+pub struct FastaReader<R> {
+    reader: R,
+    at_line_start: bool,
+    scratch: Vec<u8>,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            at_line_start: true,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the header of the next record, skipping blank lines. Returns `None` at the end of the input.
+    fn read_header(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            trim_line_end(&mut line);
+            match line.first() {
+                None => continue,
+                Some(b'>') => {
+                    line.remove(0);
+                    self.at_line_start = true;
+                    return Ok(Some(line));
+                }
+                Some(_) => {
+                    return Err(invalid_data("expected '>' at the start of a FASTA record"));
+                }
+            }
+        }
+    }
+
+    /// Feed the sequence lines of the current record to `f`, as they are buffered and with line
+    /// endings still in place. Stops before the `>` of the next record.
+    fn read_sequence(&mut self, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+
+            let end = find_record_end(buf, self.at_line_start);
+            let len = end.unwrap_or(buf.len());
+            f(&buf[..len]);
+            if len > 0 {
+                self.at_line_start = buf[len - 1] == b'\n';
+            }
+            self.reader.consume(len);
+            if end.is_some() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read the next record, replacing the contents of `seq` with its sequence without line
+    /// endings. Returns the header, or `None` at the end of the input.
+    pub fn read_raw(&mut self, seq: &mut Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        seq.clear();
+        let Some(header) = self.read_header()? else {
+            return Ok(None);
+        };
+        self.read_sequence(|lines| strip_newlines(lines, seq))?;
+        Ok(Some(header))
+    }
+
+    /// Read the next record through `converter`, replacing the contents of `out`.
+    ///
+    /// The sequence is converted while it is read, so `converter` must write one byte per base
+    /// like the 2-bit [`CodeConverter`]s. Returns the header, or `None` at the end of the input.
+    pub fn read_converted<C: CodeConverter>(
+        &mut self,
+        converter: &C,
+        out: &mut Vec<u8>,
+    ) -> io::Result<Option<Vec<u8>>> {
+        out.clear();
+        let Some(header) = self.read_header()? else {
+            return Ok(None);
+        };
+
+        let mut scratch = core::mem::take(&mut self.scratch);
+        let result = self.read_sequence(|lines| {
+            scratch.clear();
+            strip_newlines(lines, &mut scratch);
+            let start = out.len();
+            out.resize(start + scratch.len(), 0);
+            converter.convert(&scratch, &mut out[start..]);
+        });
+        self.scratch = scratch;
+        result?;
+        Ok(Some(header))
+    }
+
+    /// Read the next record and pack it in place with `packer`, so only one copy of the sequence
+    /// is held. Returns `None` at the end of the input.
+    pub fn read_packed<P: PackInPlace>(
+        &mut self,
+        packer: &P,
+    ) -> io::Result<Option<Record<PackedSequence>>> {
+        let mut seq = Vec::new();
+        let Some(header) = self.read_raw(&mut seq)? else {
+            return Ok(None);
+        };
+        Ok(Some(Record {
+            header,
+            seq: packer.pack_vec(seq),
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = io::Result<Record<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut seq = Vec::new();
+        match self.read_raw(&mut seq) {
+            Ok(Some(header)) => Some(Ok(Record { header, seq })),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Bytes buffered by [`FastaWriter`] before they are written out.
+const WRITE_BUF_SIZE: usize = 64 * 1024;
+
+/// Writes FASTA records to a [`Write`], wrapping the sequence at a fixed line width.
+pub struct FastaWriter<W> {
+    writer: W,
+    line_width: usize,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> FastaWriter<W> {
+    /// A writer with 60 bases per line.
+    pub fn new(writer: W) -> Self {
+        Self::with_line_width(writer, 60)
+    }
+
+    /// A writer with `line_width` bases per line, or the whole sequence on one line if it is 0.
+    pub fn with_line_width(writer: W, line_width: usize) -> Self {
+        Self {
+            writer,
+            line_width,
+            buf: Vec::with_capacity(WRITE_BUF_SIZE),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write a record with an ASCII sequence.
+    pub fn write_record(&mut self, header: &[u8], seq: &[u8]) -> io::Result<()> {
+        self.write_with(header, seq.len(), |range, out| {
+            out.copy_from_slice(&seq[range])
+        })
+    }
+
+    /// Write a record whose sequence `converter` turns into ASCII, one byte per base.
+    pub fn write_converted<C: CodeConverter>(
+        &mut self,
+        header: &[u8],
+        seq: &[u8],
+        converter: &C,
+    ) -> io::Result<()> {
+        self.write_with(header, seq.len(), |range, out| {
+            converter.convert(&seq[range], out)
+        })
+    }
+
+    /// Write a record of the 0..3 codes emitted by the [`CodeConverter`]s, other bytes are
+    /// written as N.
+    pub fn write_codes(&mut self, header: &[u8], codes: &[u8]) -> io::Result<()> {
+        self.write_converted(header, codes, &ShuffleCodeToAscii)
+    }
+
+    /// Write a packed record, unpacking each line with `unpacker`.
+    pub fn write_packed<U: Unpack>(
+        &mut self,
+        header: &[u8],
+        packed: PackedSlice<'_>,
+        unpacker: &U,
+    ) -> io::Result<()> {
+        self.write_with(header, packed.len(), |range, out| {
+            unpacker.unpack(packed.slice(range), out)
+        })
+    }
+
+    /// Write the header, then let `fill` write the bases in each range into the line buffer.
+    fn write_with(
+        &mut self,
+        header: &[u8],
+        len: usize,
+        mut fill: impl FnMut(Range<usize>, &mut [u8]),
+    ) -> io::Result<()> {
+        self.buf.clear();
+        self.buf.push(b'>');
+        self.buf.extend_from_slice(header);
+        self.buf.push(b'\n');
+
+        let width = if self.line_width == 0 {
+            len.max(1)
+        } else {
+            self.line_width
+        };
+        let mut start = 0;
+        while start < len {
+            // leave room for a newline
+            if WRITE_BUF_SIZE.saturating_sub(self.buf.len()) < 2 {
+                self.writer.write_all(&self.buf)?;
+                self.buf.clear();
+            }
+            let line_end = ((start / width + 1) * width).min(len);
+            let end = line_end.min(start + WRITE_BUF_SIZE - self.buf.len() - 1);
+
+            let pos = self.buf.len();
+            self.buf.resize(pos + end - start, 0);
+            fill(start..end, &mut self.buf[pos..]);
+            if end == line_end {
+                self.buf.push(b'\n');
+            }
+            start = end;
+        }
+
+        self.writer.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Maps the 0..3 codes back to ASCII, one byte at a time.
+struct CodeToAscii;
+
+impl CodeConverter for CodeToAscii {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        for (c, o) in code.iter().zip(out) {
+            *o = *b"ATCG".get(*c as usize).unwrap_or(&b'N');
+        }
+    }
+}
+
+/// The ASCII base of each code, indexed by the code saturated to 4.
+static CODE_TO_ASCII: Align64<[u8; 16]> = Align64(*b"ATCGNNNNNNNNNNNN");
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(x86_ssse3, "ssse3");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(x86_avx2, "avx2");
+
+/// Same as [`CodeToAscii`], with a byte shuffle over 16 or 32 codes at a time.
+struct ShuffleCodeToAscii;
+
+impl CodeConverter for ShuffleCodeToAscii {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        assert!(out.len() >= code.len());
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if x86_avx2::get() {
+                unsafe { avx2_code_to_ascii(code, out) }
+            } else if x86_ssse3::get() {
+                unsafe { ssse3_code_to_ascii(code, out) }
+            } else {
+                CodeToAscii.convert(code, out)
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if cfg!(target_feature = "neon") {
+                unsafe { neon_code_to_ascii(code, out) }
+            } else {
+                CodeToAscii.convert(code, out)
+            }
+        }
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        {
+            simd128_code_to_ascii(code, out)
+        }
+        #[cfg(not(any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )))]
+        {
+            CodeToAscii.convert(code, out)
+        }
+    }
+}
+
+/// Same as [`CodeToAscii`], 16 codes at a time.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "ssse3")]
+fn ssse3_code_to_ascii(code: &[u8], out: &mut [u8]) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let simd_len = code.len() / 16;
+    let (code_simd, code_rest) = code.split_at(simd_len * 16);
+    let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+    unsafe {
+        let lut = _mm_load_si128(CODE_TO_ASCII.0.as_ptr().cast());
+        let max = _mm_set1_epi8(4);
+        for (chunk, out_chunk) in code_simd
+            .chunks_exact(16)
+            .zip(out_simd.chunks_exact_mut(16))
+        {
+            let idx = _mm_min_epu8(_mm_loadu_si128(chunk.as_ptr().cast()), max);
+            _mm_storeu_si128(out_chunk.as_mut_ptr().cast(), _mm_shuffle_epi8(lut, idx));
+        }
+    }
+
+    CodeToAscii.convert(code_rest, out_rest);
+}
+
+/// Same as [`CodeToAscii`], 32 codes at a time.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[target_feature(enable = "avx2")]
+fn avx2_code_to_ascii(code: &[u8], out: &mut [u8]) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let simd_len = code.len() / 32;
+    let (code_simd, code_rest) = code.split_at(simd_len * 32);
+    let (out_simd, out_rest) = out.split_at_mut(simd_len * 32);
+
+    unsafe {
+        let lut = _mm256_broadcastsi128_si256(_mm_load_si128(CODE_TO_ASCII.0.as_ptr().cast()));
+        let max = _mm256_set1_epi8(4);
+        for (chunk, out_chunk) in code_simd
+            .chunks_exact(32)
+            .zip(out_simd.chunks_exact_mut(32))
+        {
+            let idx = _mm256_min_epu8(_mm256_loadu_si256(chunk.as_ptr().cast()), max);
+            _mm256_storeu_si256(out_chunk.as_mut_ptr().cast(), _mm256_shuffle_epi8(lut, idx));
+        }
+    }
+
+    ssse3_code_to_ascii(code_rest, out_rest);
+}
+
+/// Same as [`CodeToAscii`], 16 codes at a time.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn neon_code_to_ascii(code: &[u8], out: &mut [u8]) {
+    use core::arch::aarch64::*;
+
+    let simd_len = code.len() / 16;
+    let (code_simd, code_rest) = code.split_at(simd_len * 16);
+    let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+    unsafe {
+        let lut = vld1q_u8(CODE_TO_ASCII.0.as_ptr());
+        let max = vdupq_n_u8(4);
+        for (chunk, out_chunk) in code_simd
+            .chunks_exact(16)
+            .zip(out_simd.chunks_exact_mut(16))
+        {
+            let idx = vminq_u8(vld1q_u8(chunk.as_ptr()), max);
+            vst1q_u8(out_chunk.as_mut_ptr(), vqtbl1q_u8(lut, idx));
+        }
+    }
+
+    CodeToAscii.convert(code_rest, out_rest);
+}
+
+/// Same as [`CodeToAscii`], 16 codes at a time.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn simd128_code_to_ascii(code: &[u8], out: &mut [u8]) {
+    use core::arch::wasm32::*;
+
+    let simd_len = code.len() / 16;
+    let (code_simd, code_rest) = code.split_at(simd_len * 16);
+    let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+    unsafe {
+        let lut = v128_load(CODE_TO_ASCII.0.as_ptr().cast());
+        let max = u8x16_splat(4);
+        for (chunk, out_chunk) in code_simd
+            .chunks_exact(16)
+            .zip(out_simd.chunks_exact_mut(16))
+        {
+            let idx = u8x16_min(v128_load(chunk.as_ptr().cast()), max);
+            v128_store(out_chunk.as_mut_ptr().cast(), u8x16_swizzle(lut, idx));
+        }
+    }
+
+    CodeToAscii.convert(code_rest, out_rest);
+}
+
+fn trim_line_end(line: &mut Vec<u8>) {
+    while let Some(b'\n' | b'\r') = line.last() {
+        line.pop();
+    }
+}
+
+/// The offset of the first `>` in `buf` that starts a line.
+fn find_record_end(buf: &[u8], at_line_start: bool) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(idx) = find_any(&buf[offset..], b'>', b'>') {
+        let idx = offset + idx;
+        let line_start = if idx == 0 {
+            at_line_start
+        } else {
+            buf[idx - 1] == b'\n'
+        };
+        if line_start {
+            return Some(idx);
+        }
+        offset = idx + 1;
+    }
+    None
+}
+
+/// Append `input` to `out` without `\n` and `\r`.
+pub fn strip_newlines(input: &[u8], out: &mut Vec<u8>) {
+    out.reserve(input.len());
+
+    let mut rest = input;
+    while let Some(idx) = find_any(rest, b'\n', b'\r') {
+        out.extend_from_slice(&rest[..idx]);
+        rest = &rest[idx + 1..];
+    }
+    out.extend_from_slice(rest);
+}
+
+/// The offset of the first `a` or `b` in `buf`.
+fn find_any(buf: &[u8], a: u8, b: u8) -> Option<usize> {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        sse2_find_any(buf, a, b)
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if cfg!(target_feature = "neon") {
+            unsafe { neon_find_any(buf, a, b) }
+        } else {
+            find_any_scalar(buf, a, b)
+        }
+    }
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    {
+        simd128_find_any(buf, a, b)
+    }
+    #[cfg(not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )))]
+    {
+        find_any_scalar(buf, a, b)
+    }
+}
+
+fn find_any_scalar(buf: &[u8], a: u8, b: u8) -> Option<usize> {
+    buf.iter().position(|&c| c == a || c == b)
+}
+
+/// Same as [`find_any_scalar`], 16 bytes at a time.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn sse2_find_any(buf: &[u8], a: u8, b: u8) -> Option<usize> {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let simd_len = buf.len() / 16;
+    let (buf_simd, buf_rest) = buf.split_at(simd_len * 16);
+
+    unsafe {
+        let (a, b) = (_mm_set1_epi8(a as _), _mm_set1_epi8(b as _));
+        for (i, chunk) in buf_simd.chunks_exact(16).enumerate() {
+            let chunk = _mm_loadu_si128(chunk.as_ptr().cast());
+            let found = _mm_movemask_epi8(_mm_or_si128(
+                _mm_cmpeq_epi8(chunk, a),
+                _mm_cmpeq_epi8(chunk, b),
+            ));
+            if found != 0 {
+                return Some(i * 16 + found.trailing_zeros() as usize);
+            }
+        }
+    }
+
+    find_any_scalar(buf_rest, a, b).map(|idx| simd_len * 16 + idx)
+}
+
+/// Same as [`find_any_scalar`], 16 bytes at a time.
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn neon_find_any(buf: &[u8], a: u8, b: u8) -> Option<usize> {
+    use core::arch::aarch64::*;
+
+    let simd_len = buf.len() / 16;
+    let (buf_simd, buf_rest) = buf.split_at(simd_len * 16);
+
+    unsafe {
+        let (va, vb) = (vdupq_n_u8(a), vdupq_n_u8(b));
+        for (i, chunk) in buf_simd.chunks_exact(16).enumerate() {
+            let v = vld1q_u8(chunk.as_ptr());
+            // there is no movemask, so only locate the byte once the chunk is known to have it
+            if vmaxvq_u8(vorrq_u8(vceqq_u8(v, va), vceqq_u8(v, vb))) != 0 {
+                return find_any_scalar(chunk, a, b).map(|idx| i * 16 + idx);
+            }
+        }
+    }
+
+    find_any_scalar(buf_rest, a, b).map(|idx| simd_len * 16 + idx)
+}
+
+/// Same as [`find_any_scalar`], 16 bytes at a time.
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn simd128_find_any(buf: &[u8], a: u8, b: u8) -> Option<usize> {
+    use core::arch::wasm32::*;
+
+    let simd_len = buf.len() / 16;
+    let (buf_simd, buf_rest) = buf.split_at(simd_len * 16);
+
+    unsafe {
+        let (va, vb) = (u8x16_splat(a), u8x16_splat(b));
+        for (i, chunk) in buf_simd.chunks_exact(16).enumerate() {
+            let v = v128_load(chunk.as_ptr().cast());
+            let found = u8x16_bitmask(v128_or(i8x16_eq(v, va), i8x16_eq(v, vb)));
+            if found != 0 {
+                return Some(i * 16 + found.trailing_zeros() as usize);
+            }
+        }
+    }
+
+    find_any_scalar(buf_rest, a, b).map(|idx| simd_len * 16 + idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NaiveCodeConverter;
+    use crate::custom_alphabet::{LUTInPlacePacker, LUTUnpacker, PackInPlace};
+    use rand::{Rng, SeedableRng};
+    use std::io::BufReader;
+
+    fn random_fasta(rng: &mut impl Rng) -> (Vec<u8>, Vec<Record<Vec<u8>>>) {
+        let mut fasta = Vec::new();
+        let mut records = Vec::new();
+        for i in 0..rng.random_range(1..6) {
+            let header = format!("chr{} description {}", i, rng.random::<u32>()).into_bytes();
+            let seq: Vec<u8> = (0..rng.random_range(0..500))
+                .map(|_| b"ACGTNacgtn"[rng.random_range(0..10)])
+                .collect();
+            let width = rng.random_range(1..100);
+            let newline: &[u8] = if rng.random() { b"\r\n" } else { b"\n" };
+
+            fasta.push(b'>');
+            fasta.extend_from_slice(&header);
+            fasta.extend_from_slice(newline);
+            for line in seq.chunks(width) {
+                fasta.extend_from_slice(line);
+                fasta.extend_from_slice(newline);
+            }
+            if rng.random_range(0..4) == 0 {
+                fasta.extend_from_slice(newline);
+            }
+            records.push(Record { header, seq });
+        }
+        (fasta, records)
+    }
+
+    #[test]
+    fn test_read_records() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for _ in 0..50 {
+            let (fasta, expected) = random_fasta(&mut rng);
+            // small buffers split lines, headers and record boundaries
+            for capacity in [1, 7, 64, 8192] {
+                let reader = FastaReader::new(BufReader::with_capacity(capacity, &fasta[..]));
+                let records = reader.collect::<io::Result<Vec<_>>>().unwrap();
+                assert_eq!(records, expected, "capacity {}", capacity);
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_converted_and_packed() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(2);
+        for _ in 0..20 {
+            let (fasta, expected) = random_fasta(&mut rng);
+
+            let converter = NaiveCodeConverter::default();
+            let mut reader = FastaReader::new(BufReader::with_capacity(13, &fasta[..]));
+            let mut out = Vec::new();
+            for record in &expected {
+                let header = reader.read_converted(&converter, &mut out).unwrap();
+                assert_eq!(header.as_ref(), Some(&record.header));
+                let mut codes = vec![0; record.seq.len()];
+                converter.convert(&record.seq, &mut codes);
+                assert_eq!(out, codes);
+            }
+            assert_eq!(reader.read_converted(&converter, &mut out).unwrap(), None);
+
+            let mut reader = FastaReader::new(&fasta[..]);
+            for record in &expected {
+                let packed = reader
+                    .read_packed(&LUTInPlacePacker::default())
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(packed.name(), record.name());
+                let unpacked = LUTUnpacker::default().unpack_to_vec(packed.seq.as_slice());
+                assert_eq!(unpacked, record.seq.to_ascii_uppercase());
+            }
+            assert!(
+                reader
+                    .read_packed(&LUTInPlacePacker::default())
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed() {
+        let mut reader = FastaReader::new(&b"\n\nACGT\n>chr1\nACGT\n"[..]);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a '>' inside a line is not a record boundary
+        let records = FastaReader::new(&b">a\nAC>GT\n>b\n"[..])
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].seq, b"AC>GT");
+        assert!(records[1].seq.is_empty());
+    }
+
+    fn test_write_packed_with<U: Unpack>(unpacker: &U) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(4);
+        for _ in 0..20 {
+            let width = rng.random_range(1..100);
+            let mut expected = Vec::new();
+            let mut out = FastaWriter::with_line_width(Vec::new(), width);
+            let mut packed = Vec::new();
+            for i in 0..rng.random_range(1..4) {
+                let header = format!("chr{}", i).into_bytes();
+                let seq: Vec<u8> = (0..rng.random_range(0..1000))
+                    .map(|_| b"ACGTN"[rng.random_range(0..5)])
+                    .collect();
+
+                expected.push(b'>');
+                expected.extend_from_slice(&header);
+                expected.push(b'\n');
+                for line in seq.chunks(width) {
+                    expected.extend_from_slice(line);
+                    expected.push(b'\n');
+                }
+
+                packed.clear();
+                packed.extend_from_slice(&seq);
+                let packed = LUTInPlacePacker::default().pack_in_place(&mut packed);
+                out.write_packed(&header, packed, unpacker).unwrap();
+            }
+            assert_eq!(out.into_inner(), expected, "width {}", width);
+        }
+    }
+
+    #[test]
+    fn test_write_packed() {
+        test_write_packed_with(&LUTUnpacker::default());
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        test_write_packed_with(&crate::custom_alphabet::SSSE3Unpacker::default());
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(5);
+        let (_, records) = random_fasta(&mut rng);
+        let converter = NaiveCodeConverter::default();
+        for width in [0, 1, 60, 80] {
+            let mut writer = FastaWriter::with_line_width(Vec::new(), width);
+            for record in &records {
+                writer.write_record(&record.header, &record.seq).unwrap();
+            }
+            let fasta = writer.into_inner();
+            if width > 0 {
+                assert!(
+                    fasta
+                        .split(|&b| b == b'\n')
+                        .all(|line| { line.first() == Some(&b'>') || line.len() <= width })
+                );
+            }
+            let read = FastaReader::new(&fasta[..])
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(read, records, "width {}", width);
+
+            let mut writer = FastaWriter::with_line_width(Vec::new(), width);
+            for record in &records {
+                let mut codes = vec![0; record.seq.len()];
+                converter.convert(&record.seq, &mut codes);
+                writer.write_codes(&record.header, &codes).unwrap();
+            }
+            let fasta = writer.into_inner();
+            let read = FastaReader::new(&fasta[..])
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            for (read, record) in read.iter().zip(&records) {
+                assert_eq!(read.seq, record.seq.to_ascii_uppercase());
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_long_line() {
+        let seq = vec![b'A'; WRITE_BUF_SIZE * 2 + 3];
+        let mut writer = FastaWriter::with_line_width(Vec::new(), 0);
+        writer.write_record(b"long", &seq).unwrap();
+        let fasta = writer.into_inner();
+        assert_eq!(fasta.len(), 6 + seq.len() + 1);
+        assert_eq!(&fasta[6..fasta.len() - 1], &seq[..]);
+        assert_eq!(fasta.last(), Some(&b'\n'));
+    }
+
+    #[test]
+    fn test_write_long_header() {
+        let header = vec![b'h'; WRITE_BUF_SIZE + 4464];
+        let mut writer = FastaWriter::with_line_width(Vec::new(), 4);
+        writer.write_record(&header, b"ACGTAC").unwrap();
+        let fasta = writer.into_inner();
+        assert_eq!(fasta[1..header.len() + 1], header);
+        assert_eq!(&fasta[header.len() + 1..], b"\nACGT\nAC\n");
+    }
+
+    #[test]
+    fn test_code_to_ascii() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(4);
+        for n in [0, 1, 15, 16, 17, 31, 32, 33, 100, 1000] {
+            let code: Vec<u8> = (0..n)
+                .map(|_| {
+                    if rng.random() {
+                        rng.random_range(0..5)
+                    } else {
+                        rng.random()
+                    }
+                })
+                .collect();
+            let mut expected = vec![0; n];
+            CodeToAscii.convert(&code, &mut expected);
+
+            let mut out = vec![0; n];
+            ShuffleCodeToAscii.convert(&code, &mut out);
+            assert_eq!(out, expected);
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            if x86_ssse3::get() {
+                let mut out = vec![0; n];
+                unsafe { ssse3_code_to_ascii(&code, &mut out) };
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_strip_newlines() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(3);
+        for n in [0, 1, 15, 16, 17, 100, 1000] {
+            let input: Vec<u8> = (0..n)
+                .map(|_| b"ACGT\n\r"[rng.random_range(0..6)])
+                .collect();
+            let mut out = vec![b'x'];
+            strip_newlines(&input, &mut out);
+            let expected: Vec<u8> = core::iter::once(b'x')
+                .chain(input.iter().copied().filter(|&b| b != b'\n' && b != b'\r'))
+                .collect();
+            assert_eq!(out, expected);
+        }
+    }
+}
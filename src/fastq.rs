@@ -0,0 +1,517 @@
+//! Streaming FASTQ reader.
+//!
+//! Records are read one at a time from any [`BufRead`], so reads can be streamed from stdin, and
+//! appended to a [`ReadArena`] that is cleared and reused between batches:
+//!
+//! ```no_run
+//! use nucleotide_converter::NaiveCodeConverter;
+//! use nucleotide_converter::fastq::{FastqReader, ReadArena};
+//!
+//! let mut reader = FastqReader::new(std::io::stdin().lock());
+//! let mut arena = ReadArena::default();
+//! let converter = NaiveCodeConverter::default();
+//! loop {
+//!     arena.clear();
+//!     while arena.len() < 4096 && reader.read_converted(&converter, &mut arena)? {}
+//!     if arena.is_empty() {
+//!         break;
+//!     }
+//!     for read in arena.iter() {
+//!         // ...
+//!     }
+//! }
+//! # Ok::<(), nucleotide_converter::fastq::FastqError>(())
+//! ```
+
+use core::ops::Range;
+use std::io::{self, BufRead};
+
+use crate::CodeConverter;
+use crate::custom_alphabet::Pack;
+use crate::packed::PackedSlice;
+
+/// Highest quality that Phred+33 can encode, `~`.
+pub const MAX_PHRED: u8 = b'~' - b'!';
+
+/// An error while reading FASTQ. `line` is the 1-based line number.
+#[derive(Debug)]
+pub enum FastqError {
+    Io(io::Error),
+    /// A record does not start with `@`.
+    MissingHeader {
+        line: u64,
+    },
+    /// The third line of a record does not start with `+`.
+    MissingSeparator {
+        line: u64,
+    },
+    /// The input ends inside a record.
+    Truncated {
+        line: u64,
+    },
+    /// The quality string is not as long as the sequence.
+    LengthMismatch {
+        line: u64,
+        seq_len: usize,
+        qual_len: usize,
+    },
+    /// A quality byte outside of `!..=~`.
+    InvalidQuality {
+        line: u64,
+        position: usize,
+        byte: u8,
+    },
+}
+
+impl core::fmt::Display for FastqError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::MissingHeader { line } => write!(f, "expected '@' at line {}", line),
+            Self::MissingSeparator { line } => write!(f, "expected '+' at line {}", line),
+            Self::Truncated { line } => write!(f, "truncated record at line {}", line),
+            Self::LengthMismatch {
+                line,
+                seq_len,
+                qual_len,
+            } => write!(
+                f,
+                "quality length {} does not match sequence length {} at line {}",
+                qual_len, seq_len, line
+            ),
+            Self::InvalidQuality {
+                line,
+                position,
+                byte,
+            } => write!(
+                f,
+                "invalid quality '{}' at line {}, position {}",
+                byte.escape_ascii(),
+                line,
+                position
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FastqError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FastqError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A read stored in a [`ReadArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Read<'a> {
+    /// The header line without the leading `@` and the line ending.
+    pub header: &'a [u8],
+    /// The converted or packed sequence.
+    pub seq: &'a [u8],
+    /// Phred qualities, one per base.
+    pub qual: &'a [u8],
+}
+
+impl<'a> Read<'a> {
+    /// Number of bases.
+    pub fn len(&self) -> usize {
+        self.qual.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.qual.is_empty()
+    }
+
+    /// The sequence of a read appended with [`FastqReader::read_packed`].
+    pub fn packed(&self) -> PackedSlice<'a> {
+        PackedSlice::new(self.seq, self.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Spans {
+    header: Range<usize>,
+    seq: Range<usize>,
+    qual: Range<usize>,
+}
+
+/// Reads stored back to back in a few growable buffers.
+///
+/// [`clear`](Self::clear) keeps the allocations, so a batch loop stops allocating once the
+/// buffers have grown to the batch size.
+#[derive(Debug, Clone, Default)]
+pub struct ReadArena {
+    headers: Vec<u8>,
+    seqs: Vec<u8>,
+    quals: Vec<u8>,
+    reads: Vec<Spans>,
+}
+
+impl ReadArena {
+    /// Number of reads.
+    pub fn len(&self) -> usize {
+        self.reads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reads.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.headers.clear();
+        self.seqs.clear();
+        self.quals.clear();
+        self.reads.clear();
+    }
+
+    pub fn get(&self, i: usize) -> Option<Read<'_>> {
+        self.reads.get(i).map(|spans| Read {
+            header: &self.headers[spans.header.clone()],
+            seq: &self.seqs[spans.seq.clone()],
+            qual: &self.quals[spans.qual.clone()],
+        })
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Read<'_>> {
+        (0..self.len()).map(|i| self.get(i).unwrap())
+    }
+}
+
+/// Reads FASTQ records with single-line sequences and qualities from a [`BufRead`].
+pub struct FastqReader<R> {
+    reader: R,
+    line: u64,
+    header: Vec<u8>,
+    seq: Vec<u8>,
+    separator: Vec<u8>,
+    qual: Vec<u8>,
+}
+
+impl<R: BufRead> FastqReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: 0,
+            header: Vec::new(),
+            seq: Vec::new(),
+            separator: Vec::new(),
+            qual: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the next record and append it to `arena`, converting the sequence with `converter`
+    /// which must write one byte per base. Returns `false` at the end of the input.
+    pub fn read_converted<C: CodeConverter>(
+        &mut self,
+        converter: &C,
+        arena: &mut ReadArena,
+    ) -> Result<bool, FastqError> {
+        self.read_with(arena, |len| len, |seq, out| converter.convert(seq, out))
+    }
+
+    /// Read the next record and append it to `arena`, packing the sequence with `packer`.
+    /// Returns `false` at the end of the input.
+    pub fn read_packed<P: Pack>(
+        &mut self,
+        packer: &P,
+        arena: &mut ReadArena,
+    ) -> Result<bool, FastqError> {
+        self.read_with(
+            arena,
+            |len| len.div_ceil(2),
+            |seq, out| packer.convert(seq, out),
+        )
+    }
+
+    /// Read one line into `buf` without its line ending, returns `false` at the end of the input.
+    fn read_line(reader: &mut R, line: &mut u64, buf: &mut Vec<u8>) -> io::Result<bool> {
+        buf.clear();
+        if reader.read_until(b'\n', buf)? == 0 {
+            return Ok(false);
+        }
+        *line += 1;
+        while let Some(b'\n' | b'\r') = buf.last() {
+            buf.pop();
+        }
+        Ok(true)
+    }
+
+    fn read_with(
+        &mut self,
+        arena: &mut ReadArena,
+        out_len: impl Fn(usize) -> usize,
+        convert: impl FnOnce(&[u8], &mut [u8]),
+    ) -> Result<bool, FastqError> {
+        let Self {
+            reader,
+            line,
+            header,
+            seq,
+            separator,
+            qual,
+        } = self;
+
+        // blank lines are only allowed between records
+        loop {
+            if !Self::read_line(reader, line, header)? {
+                return Ok(false);
+            }
+            if !header.is_empty() {
+                break;
+            }
+        }
+        if header[0] != b'@' {
+            return Err(FastqError::MissingHeader { line: *line });
+        }
+        for buf in [&mut *seq, &mut *separator, &mut *qual] {
+            if !Self::read_line(reader, line, buf)? {
+                return Err(FastqError::Truncated { line: *line });
+            }
+        }
+        if separator.first() != Some(&b'+') {
+            return Err(FastqError::MissingSeparator { line: *line - 1 });
+        }
+        if seq.len() != qual.len() {
+            return Err(FastqError::LengthMismatch {
+                line: *line,
+                seq_len: seq.len(),
+                qual_len: qual.len(),
+            });
+        }
+
+        let qual_start = arena.quals.len();
+        arena.quals.resize(qual_start + qual.len(), 0);
+        if let Some(position) = decode_phred33(qual, &mut arena.quals[qual_start..]) {
+            arena.quals.truncate(qual_start);
+            return Err(FastqError::InvalidQuality {
+                line: *line,
+                position,
+                byte: qual[position],
+            });
+        }
+
+        let seq_start = arena.seqs.len();
+        arena.seqs.resize(seq_start + out_len(seq.len()), 0);
+        convert(seq, &mut arena.seqs[seq_start..]);
+
+        let header_start = arena.headers.len();
+        arena.headers.extend_from_slice(&header[1..]);
+
+        arena.reads.push(Spans {
+            header: header_start..arena.headers.len(),
+            seq: seq_start..arena.seqs.len(),
+            qual: qual_start..arena.quals.len(),
+        });
+        Ok(true)
+    }
+}
+
+/// Decode Phred+33 qualities from `qual` into `out`, returning the offset of the first byte
+/// outside of `!..=~`.
+pub fn decode_phred33(qual: &[u8], out: &mut [u8]) -> Option<usize> {
+    assert!(out.len() >= qual.len());
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        sse2_decode_phred33(qual, out)
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    {
+        decode_phred33_scalar(qual, out)
+    }
+}
+
+fn decode_phred33_scalar(qual: &[u8], out: &mut [u8]) -> Option<usize> {
+    for (i, (q, o)) in qual.iter().zip(out).enumerate() {
+        *o = q.wrapping_sub(b'!');
+        if *o > MAX_PHRED {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Same as [`decode_phred33_scalar`], 16 bytes at a time.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn sse2_decode_phred33(qual: &[u8], out: &mut [u8]) -> Option<usize> {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let simd_len = qual.len() / 16;
+    let (qual_simd, qual_rest) = qual.split_at(simd_len * 16);
+    let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+    unsafe {
+        let (offset, max) = (_mm_set1_epi8(b'!' as _), _mm_set1_epi8(MAX_PHRED as _));
+        for (i, (chunk, out_chunk)) in qual_simd
+            .chunks_exact(16)
+            .zip(out_simd.chunks_exact_mut(16))
+            .enumerate()
+        {
+            let q = _mm_sub_epi8(_mm_loadu_si128(chunk.as_ptr().cast()), offset);
+            _mm_storeu_si128(out_chunk.as_mut_ptr().cast(), q);
+
+            let valid = _mm_movemask_epi8(_mm_cmpeq_epi8(_mm_min_epu8(q, max), q));
+            if valid != 0xffff {
+                return Some(i * 16 + (!valid).trailing_zeros() as usize);
+            }
+        }
+    }
+
+    decode_phred33_scalar(qual_rest, out_rest).map(|idx| simd_len * 16 + idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NaiveCodeConverter;
+    use crate::custom_alphabet::{LUTPacker, LUTUnpacker, Unpack};
+    use rand::{Rng, SeedableRng};
+    use std::io::BufReader;
+
+    struct Expected {
+        header: Vec<u8>,
+        seq: Vec<u8>,
+        qual: Vec<u8>,
+    }
+
+    fn random_fastq(rng: &mut impl Rng, n: usize) -> (Vec<u8>, Vec<Expected>) {
+        let mut fastq = Vec::new();
+        let mut reads = Vec::new();
+        for i in 0..n {
+            let header = format!("read{} 1:N:0:{}", i, rng.random::<u16>()).into_bytes();
+            let len = rng.random_range(0..300);
+            let seq: Vec<u8> = (0..len).map(|_| b"ACGTN"[rng.random_range(0..5)]).collect();
+            let qual: Vec<u8> = (0..len).map(|_| rng.random_range(0..=MAX_PHRED)).collect();
+            let newline: &[u8] = if rng.random() { b"\r\n" } else { b"\n" };
+
+            for line in [
+                &[b"@", &header[..]].concat()[..],
+                &seq,
+                b"+",
+                &qual.iter().map(|q| q + b'!').collect::<Vec<_>>(),
+            ] {
+                fastq.extend_from_slice(line);
+                fastq.extend_from_slice(newline);
+            }
+            reads.push(Expected { header, seq, qual });
+        }
+        (fastq, reads)
+    }
+
+    #[test]
+    fn test_read_into_arena() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let (fastq, expected) = random_fastq(&mut rng, 100);
+        let converter = NaiveCodeConverter::default();
+
+        let mut reader = FastqReader::new(BufReader::with_capacity(17, &fastq[..]));
+        let mut arena = ReadArena::default();
+        // batches of 30 reuse the arena
+        for batch in expected.chunks(30) {
+            arena.clear();
+            while arena.len() < 30 && reader.read_converted(&converter, &mut arena).unwrap() {}
+            assert_eq!(arena.len(), batch.len());
+            for (read, expected) in arena.iter().zip(batch) {
+                let mut codes = vec![0; expected.seq.len()];
+                converter.convert(&expected.seq, &mut codes);
+                assert_eq!(read.header, expected.header);
+                assert_eq!(read.seq, codes);
+                assert_eq!(read.qual, expected.qual);
+            }
+        }
+        assert!(!reader.read_converted(&converter, &mut arena).unwrap());
+
+        let mut reader = FastqReader::new(&fastq[..]);
+        let mut arena = ReadArena::default();
+        while reader
+            .read_packed(&LUTPacker::default(), &mut arena)
+            .unwrap()
+        {}
+        assert_eq!(arena.len(), expected.len());
+        for (read, expected) in arena.iter().zip(&expected) {
+            assert_eq!(
+                LUTUnpacker::default().unpack_to_vec(read.packed()),
+                expected.seq
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        fn read(fastq: &[u8]) -> Result<bool, FastqError> {
+            let mut arena = ReadArena::default();
+            let mut reader = FastqReader::new(fastq);
+            while reader.read_converted(&NaiveCodeConverter::default(), &mut arena)? {}
+            Ok(arena.is_empty())
+        }
+
+        assert!(read(b"").unwrap());
+        assert!(read(b"\n\n").unwrap());
+        assert!(!read(b"@r\nACGT\n+\nIIII\n\n").unwrap());
+        assert!(matches!(
+            read(b"@r\nACGT\n+\nIIII\nr\n"),
+            Err(FastqError::MissingHeader { line: 5 })
+        ));
+        assert!(matches!(
+            read(b"@r\nACGT\n-\nIIII\n"),
+            Err(FastqError::MissingSeparator { line: 3 })
+        ));
+        assert!(matches!(
+            read(b"@r\nACGT\n+\n"),
+            Err(FastqError::Truncated { line: 3 })
+        ));
+        assert!(matches!(
+            read(b"@r\nACGT\n+\nIII\n"),
+            Err(FastqError::LengthMismatch {
+                line: 4,
+                seq_len: 4,
+                qual_len: 3
+            })
+        ));
+        let err = read(b"@r\nACGTACGTACGTACGTACGTA\n+\nIIIIIIIIIIIIIIIII III\n").unwrap_err();
+        assert!(matches!(
+            err,
+            FastqError::InvalidQuality {
+                line: 4,
+                position: 17,
+                byte: b' '
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "invalid quality ' ' at line 4, position 17"
+        );
+    }
+
+    #[test]
+    fn test_decode_phred33() {
+        for c in 0..=255u8 {
+            for position in [0, 5, 16, 31, 40] {
+                let mut qual = vec![b'I'; 41];
+                qual[position] = c;
+                let mut out = vec![0; 41];
+                let valid = (b'!'..=b'~').contains(&c);
+                let result = decode_phred33(&qual, &mut out);
+                assert_eq!(result, (!valid).then_some(position), "byte {}", c);
+                if valid {
+                    assert_eq!(out[position], c - b'!');
+                }
+            }
+        }
+    }
+}
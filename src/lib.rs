@@ -1,26 +1,121 @@
-//#![no_std]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(clippy::all)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 #[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
 
 #[cfg(target_arch = "x86")]
 use core::arch::x86::*;
 
-use std::ops::Deref;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU8, Ordering};
 
+pub mod bam;
+#[cfg(feature = "gzip")]
+pub mod bgzf;
 pub mod custom_alphabet;
+#[cfg(feature = "std")]
+pub mod fai;
+#[cfg(feature = "std")]
+pub mod fasta;
+#[cfg(feature = "std")]
+pub mod fastq;
+pub mod packed;
+pub mod reverse_complement;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod two_bit;
+#[cfg(feature = "std")]
+pub mod twobit;
 
 pub trait CodeConverter {
     fn convert(&self, code: &[u8], out: &mut [u8]);
 }
 
+/// A [`CodeConverter`] that can reject bytes that are not bases instead of writing `255`.
+pub trait TryCodeConverter: CodeConverter {
+    /// Convert `code` into `out`, failing on the first byte that is not A, C, G or T in either case.
+    ///
+    /// Returns the number of bytes written. On error, `out` may be partially written.
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase>;
+}
+
 pub trait CodeConverterInPlace {
     fn convert_in_place<'a>(&self, code: &'a mut [u8]) -> &'a mut [u8];
 }
 
+/// A byte that is not a valid base for the converter it was given to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidBase {
+    /// Offset of the byte in the input.
+    pub position: usize,
+    pub byte: u8,
+}
+
+impl core::fmt::Display for InvalidBase {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "invalid base '{}' at position {}",
+            self.byte.escape_ascii(),
+            self.position
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InvalidBase {}
+
+/// An [`InvalidData`](std::io::ErrorKind::InvalidData) error, for malformed input to the file
+/// format readers.
+#[cfg(feature = "std")]
+pub(crate) fn invalid_data(message: &'static str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
 #[cold]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
 fn unlikely() {}
+
+/// Offset of the first byte a converter mapped to the invalid code.
+fn first_invalid(out: &[u8]) -> Option<usize> {
+    out.iter().position(|b| *b == !0)
+}
+
+/// Turn the offset of the first invalid byte reported by a kernel into the result of a `try_` method.
+#[inline(always)]
+pub(crate) fn check_invalid(code: &[u8], invalid: Option<usize>) -> Result<usize, InvalidBase> {
+    match invalid {
+        None => Ok(code.len()),
+        Some(position) => Err(InvalidBase {
+            position,
+            byte: code[position],
+        }),
+    }
+}
+
+/// Convert the tail a SIMD kernel left over, `offset` is the position of the tail in the input.
+///
+/// Returns the offset of the first invalid byte if `VALIDATE` is set.
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+#[inline(always)]
+fn convert_rest<const VALIDATE: bool>(offset: usize, code: &[u8], out: &mut [u8]) -> Option<usize> {
+    NaiveCodeConverter::default().convert(code, out);
+    if VALIDATE {
+        first_invalid(&out[..code.len()]).map(|i| offset + i)
+    } else {
+        None
+    }
+}
+
 #[derive(Default)]
 pub struct NaiveCodeConverter {
     _private: (),
@@ -40,6 +135,13 @@ impl CodeConverter for NaiveCodeConverter {
     }
 }
 
+impl TryCodeConverter for NaiveCodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        self.convert(code, out);
+        check_invalid(code, first_invalid(&out[..code.len()]))
+    }
+}
+
 #[derive(Default)]
 pub struct NaiveToLowerCodeConverter {
     _private: (),
@@ -59,6 +161,13 @@ impl CodeConverter for NaiveToLowerCodeConverter {
     }
 }
 
+impl TryCodeConverter for NaiveToLowerCodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        self.convert(code, out);
+        check_invalid(code, first_invalid(&out[..code.len()]))
+    }
+}
+
 #[derive(Default)]
 pub struct LUTCodeConverter {
     _private: (),
@@ -102,8 +211,15 @@ impl CodeConverter for LUTCodeConverter {
     }
 }
 
+impl TryCodeConverter for LUTCodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        self.convert(code, out);
+        check_invalid(code, first_invalid(&out[..code.len()]))
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 pub struct SSE2CodeConverter {
-    scalar: NaiveCodeConverter,
     a: __m128i,
     t: __m128i,
     c: __m128i,
@@ -114,6 +230,7 @@ pub struct SSE2CodeConverter {
     tolower: __m128i,
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl Default for SSE2CodeConverter {
     fn default() -> Self {
         let (a, t, c, g, one, two, three, tolower) = unsafe {
@@ -129,7 +246,6 @@ impl Default for SSE2CodeConverter {
             )
         };
         Self {
-            scalar: NaiveCodeConverter::default(),
             a,
             t,
             c,
@@ -142,20 +258,44 @@ impl Default for SSE2CodeConverter {
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 macro_rules! mm_blendv_epi8x {
     ($a:expr, $b:expr, $mask:expr) => {
         _mm_xor_si128(_mm_and_si128($b, $mask), _mm_andnot_si128($mask, $a))
     };
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl CodeConverter for SSE2CodeConverter {
     fn convert(&self, code: &[u8], out: &mut [u8]) {
+        self.convert_impl::<false>(code, out);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl TryCodeConverter for SSE2CodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        check_invalid(code, self.convert_impl::<true>(code, out))
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl SSE2CodeConverter {
+    /// Returns the offset of the first invalid byte if `VALIDATE` is set.
+    fn convert_impl<const VALIDATE: bool>(&self, code: &[u8], out: &mut [u8]) -> Option<usize> {
         assert!(out.len() >= code.len());
 
-        let mut chunks = code.chunks_exact(16);
-        let mut out_chunks = out.chunks_exact_mut(16);
+        let simd_len = code.len() / 16;
+        let (code_simd, code_rest) = code.split_at(simd_len * 16);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
         unsafe {
-            for (chunk_in, chunk_out) in (&mut chunks).zip(&mut out_chunks) {
+            let nil = _mm_set1_epi32(!0);
+            for (i, (chunk_in, chunk_out)) in code_simd
+                .chunks_exact(16)
+                .zip(out_simd.chunks_exact_mut(16))
+                .enumerate()
+            {
                 let chunk_xmm =
                     _mm_or_si128(self.tolower, _mm_loadu_si128(chunk_in.as_ptr().cast()));
 
@@ -163,33 +303,75 @@ impl CodeConverter for SSE2CodeConverter {
                 let is_t = _mm_cmpeq_epi8(chunk_xmm, self.t);
                 let is_c = _mm_cmpeq_epi8(chunk_xmm, self.c);
                 let is_g = _mm_cmpeq_epi8(chunk_xmm, self.g);
-                let mut result = _mm_andnot_si128(is_a, _mm_set1_epi32(!0));
+                let mut result = _mm_andnot_si128(is_a, nil);
                 result = mm_blendv_epi8x!(result, self.one, is_t);
                 result = mm_blendv_epi8x!(result, self.two, is_c);
                 result = mm_blendv_epi8x!(result, self.three, is_g);
                 _mm_storeu_si128(chunk_out.as_mut_ptr().cast(), result);
+
+                if VALIDATE {
+                    let invalid = _mm_movemask_epi8(_mm_cmpeq_epi8(result, nil));
+                    if invalid != 0 {
+                        return Some(i * 16 + invalid.trailing_zeros() as usize);
+                    }
+                }
             }
         }
-        let remainder = chunks.remainder();
-        let out_remainder = out_chunks.into_remainder();
 
-        self.scalar.convert(remainder, out_remainder);
+        convert_rest::<VALIDATE>(simd_len * 16, code_rest, out_rest)
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 cpufeatures::new!(x86_ssse3, "ssse3");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 cpufeatures::new!(x86_avx2, "avx2");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 cpufeatures::new!(x86_avx512vbmi, "avx512vbmi");
 
+/// Tables for the nibble-shuffle converters, indexed by bits `SHIFT..SHIFT + 4` of the input.
+///
+/// A base is valid if both lookups agree, the stored value is the code plus one.
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+struct ComputeLut<const SHIFT: usize>;
+
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+impl<const SHIFT: usize> ComputeLut<SHIFT> {
+    const TABLE: Align64<[u8; 16]> = Align64(
+        const {
+            let mut lut = [0u8; 16];
+            lut[((b'a' >> SHIFT) & 0b1111) as usize] = 1;
+            lut[((b't' >> SHIFT) & 0b1111) as usize] = 2;
+            lut[((b'c' >> SHIFT) & 0b1111) as usize] = 3;
+            lut[((b'g' >> SHIFT) & 0b1111) as usize] = 4;
+            lut[((b'A' >> SHIFT) & 0b1111) as usize] = 1;
+            lut[((b'T' >> SHIFT) & 0b1111) as usize] = 2;
+            lut[((b'C' >> SHIFT) & 0b1111) as usize] = 3;
+            lut[((b'G' >> SHIFT) & 0b1111) as usize] = 4;
+            lut
+        },
+    );
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 #[derive(Default)]
 pub struct SSSE3CodeConverter {
-    scalar: NaiveCodeConverter,
+    _private: (),
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl CodeConverter for SSSE3CodeConverter {
     fn convert(&self, code: &[u8], out: &mut [u8]) {
         if x86_ssse3::get() {
-            unsafe { self.convert_impl(code, out) }
+            unsafe { self.convert_impl::<false>(code, out) };
         } else {
             unlikely();
             let sse = SSE2CodeConverter::default();
@@ -198,37 +380,38 @@ impl CodeConverter for SSSE3CodeConverter {
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl TryCodeConverter for SSSE3CodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        if x86_ssse3::get() {
+            check_invalid(code, unsafe { self.convert_impl::<true>(code, out) })
+        } else {
+            unlikely();
+            SSE2CodeConverter::default().try_convert(code, out)
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl SSSE3CodeConverter {
+    /// Returns the offset of the first invalid byte if `VALIDATE` is set.
     #[target_feature(enable = "ssse3")]
-    fn convert_impl(&self, code: &[u8], out: &mut [u8]) {
+    fn convert_impl<const VALIDATE: bool>(&self, code: &[u8], out: &mut [u8]) -> Option<usize> {
         assert!(out.len() >= code.len());
 
-        let mut chunks = code.chunks_exact(16);
-        let mut out_chunks = out.chunks_exact_mut(16);
-
-        struct ComputeLut<const SHIFT: usize>;
-
-        impl<const SHIFT: usize> ComputeLut<SHIFT> {
-            const TABLE: Align64<[u8; 16]> = Align64(
-                const {
-                    let mut lut = [0u8; 16];
-                    lut[((b'a' >> SHIFT) & 0b1111) as usize] = 1;
-                    lut[((b't' >> SHIFT) & 0b1111) as usize] = 2;
-                    lut[((b'c' >> SHIFT) & 0b1111) as usize] = 3;
-                    lut[((b'g' >> SHIFT) & 0b1111) as usize] = 4;
-                    lut[((b'A' >> SHIFT) & 0b1111) as usize] = 1;
-                    lut[((b'T' >> SHIFT) & 0b1111) as usize] = 2;
-                    lut[((b'C' >> SHIFT) & 0b1111) as usize] = 3;
-                    lut[((b'G' >> SHIFT) & 0b1111) as usize] = 4;
-                    lut
-                },
-            );
-        }
+        let simd_len = code.len() / 16;
+        let (code_simd, code_rest) = code.split_at(simd_len * 16);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
         unsafe {
             let lut_0 = _mm_load_si128(ComputeLut::<0>::TABLE.0.as_ptr().cast());
             let lut_1 = _mm_load_si128(ComputeLut::<1>::TABLE.0.as_ptr().cast());
 
-            for (chunk_in, chunk_out) in (&mut chunks).zip(&mut out_chunks) {
+            for (i, (chunk_in, chunk_out)) in code_simd
+                .chunks_exact(16)
+                .zip(out_simd.chunks_exact_mut(16))
+                .enumerate()
+            {
                 let chunk_xmm = _mm_subs_epi8(
                     _mm_loadu_si128(chunk_in.as_ptr().cast()),
                     _mm_set1_epi8(0b100_0000), // check the 6-th bit, if it is zero this will set the sign bit
@@ -242,28 +425,33 @@ impl SSSE3CodeConverter {
 
                 let results_mask = _mm_cmpeq_epi8(result0, result1);
 
-                _mm_storeu_si128(
-                    chunk_out.as_mut_ptr().cast(),
-                    _mm_sub_epi8(_mm_and_si128(results_mask, result0), _mm_set1_epi8(1)),
-                );
+                let result = _mm_sub_epi8(_mm_and_si128(results_mask, result0), _mm_set1_epi8(1));
+                _mm_storeu_si128(chunk_out.as_mut_ptr().cast(), result);
+
+                if VALIDATE {
+                    let invalid = _mm_movemask_epi8(_mm_cmpeq_epi8(result, _mm_set1_epi8(!0)));
+                    if invalid != 0 {
+                        return Some(i * 16 + invalid.trailing_zeros() as usize);
+                    }
+                }
             }
         }
-        let remainder = chunks.remainder();
-        let out_remainder = out_chunks.into_remainder();
 
-        self.scalar.convert(remainder, out_remainder);
+        convert_rest::<VALIDATE>(simd_len * 16, code_rest, out_rest)
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 #[derive(Default)]
 pub struct AVX2CodeConverter {
     _private: (),
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl CodeConverter for AVX2CodeConverter {
     fn convert(&self, code: &[u8], out: &mut [u8]) {
         if x86_avx2::get() {
-            unsafe { self.convert_impl(code, out) }
+            unsafe { self.convert_impl::<false>(code, out) };
         } else {
             let sse = SSSE3CodeConverter::default();
             sse.convert(code, out);
@@ -271,9 +459,22 @@ impl CodeConverter for AVX2CodeConverter {
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl TryCodeConverter for AVX2CodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        if x86_avx2::get() {
+            check_invalid(code, unsafe { self.convert_impl::<true>(code, out) })
+        } else {
+            SSSE3CodeConverter::default().try_convert(code, out)
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl AVX2CodeConverter {
+    /// Returns the offset of the first invalid byte if `VALIDATE` is set.
     #[target_feature(enable = "avx2")]
-    fn convert_impl(&self, code: &[u8], out: &mut [u8]) {
+    fn convert_impl<const VALIDATE: bool>(&self, code: &[u8], out: &mut [u8]) -> Option<usize> {
         assert!(out.len() >= code.len());
 
         let (a, t, c, g, one, two, three, tolower, nil) = (
@@ -288,11 +489,16 @@ impl AVX2CodeConverter {
             _mm256_set1_epi8(!0 as _),
         );
 
-        let mut chunks = code.chunks_exact(32);
-        let mut out_chunks = out.chunks_exact_mut(32);
+        let simd_len = code.len() / 32;
+        let (code_simd, code_rest) = code.split_at(simd_len * 32);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 32);
 
         unsafe {
-            for (chunk_in, chunk_out) in (&mut chunks).zip(&mut out_chunks) {
+            for (i, (chunk_in, chunk_out)) in code_simd
+                .chunks_exact(32)
+                .zip(out_simd.chunks_exact_mut(32))
+                .enumerate()
+            {
                 let chunk_ymm =
                     _mm256_or_si256(tolower, _mm256_loadu_si256(chunk_in.as_ptr().cast()));
                 let is_a = _mm256_cmpeq_epi8(chunk_ymm, a);
@@ -304,24 +510,31 @@ impl AVX2CodeConverter {
                 result = _mm256_blendv_epi8(result, two, is_c);
                 result = _mm256_blendv_epi8(result, three, is_g);
                 _mm256_storeu_si256(chunk_out.as_mut_ptr().cast(), result);
+
+                if VALIDATE {
+                    let invalid = _mm256_movemask_epi8(_mm256_cmpeq_epi8(result, nil)) as u32;
+                    if invalid != 0 {
+                        return Some(i * 32 + invalid.trailing_zeros() as usize);
+                    }
+                }
             }
         }
-        let remainder = chunks.remainder();
-        let out_remainder = out_chunks.into_remainder();
 
-        NaiveCodeConverter::default().convert(remainder, out_remainder);
+        convert_rest::<VALIDATE>(simd_len * 32, code_rest, out_rest)
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 #[derive(Default)]
 pub struct AVX512VbmiCodeConverter {
     _private: (),
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl CodeConverter for AVX512VbmiCodeConverter {
     fn convert(&self, code: &[u8], out: &mut [u8]) {
         if x86_avx512vbmi::get() {
-            unsafe { self.convert_impl(code, out) }
+            unsafe { self.convert_impl::<false>(code, out) };
         } else {
             let fallback = AVX2CodeConverter::default();
             fallback.convert(code, out);
@@ -329,9 +542,22 @@ impl CodeConverter for AVX512VbmiCodeConverter {
     }
 }
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl TryCodeConverter for AVX512VbmiCodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        if x86_avx512vbmi::get() {
+            check_invalid(code, unsafe { self.convert_impl::<true>(code, out) })
+        } else {
+            AVX2CodeConverter::default().try_convert(code, out)
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 impl AVX512VbmiCodeConverter {
+    /// Returns the offset of the first invalid byte if `VALIDATE` is set.
     #[target_feature(enable = "avx512vbmi")]
-    fn convert_impl(&self, code: &[u8], out: &mut [u8]) {
+    fn convert_impl<const VALIDATE: bool>(&self, code: &[u8], out: &mut [u8]) -> Option<usize> {
         assert!(out.len() >= code.len());
 
         static LUT: Align64<[u8; 64]> = Align64(
@@ -350,8 +576,9 @@ impl AVX512VbmiCodeConverter {
             },
         );
 
-        let mut chunks = code.chunks_exact(64);
-        let mut out_chunks = out.chunks_exact_mut(64);
+        let simd_len = code.len() / 64;
+        let (code_simd, code_rest) = code.split_at(simd_len * 64);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 64);
 
         unsafe {
             let lut = _mm512_load_si512(LUT.0.as_ptr().cast());
@@ -359,25 +586,463 @@ impl AVX512VbmiCodeConverter {
             let offset = _mm512_set1_epi8(b'A' as _);
             let range = _mm512_set1_epi8((b'z' - b'A') as _);
 
-            for (chunk_in, chunk_out) in (&mut chunks).zip(&mut out_chunks) {
+            for (i, (chunk_in, chunk_out)) in code_simd
+                .chunks_exact(64)
+                .zip(out_simd.chunks_exact_mut(64))
+                .enumerate()
+            {
                 let chunk_zmm =
-                    _mm512_sub_epi8(_mm512_load_si512(chunk_in.as_ptr().cast()), offset);
+                    _mm512_sub_epi8(_mm512_loadu_si512(chunk_in.as_ptr().cast()), offset);
 
                 let result = _mm512_permutexvar_epi8(_mm512_min_epu8(chunk_zmm, range), lut);
                 _mm512_storeu_si512(chunk_out.as_mut_ptr().cast(), result);
+
+                if VALIDATE {
+                    let invalid = _mm512_cmpeq_epi8_mask(result, _mm512_set1_epi8(!0));
+                    if invalid != 0 {
+                        return Some(i * 64 + invalid.trailing_zeros() as usize);
+                    }
+                }
+            }
+        }
+
+        convert_rest::<VALIDATE>(simd_len * 64, code_rest, out_rest)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[derive(Default)]
+pub struct NEONCodeConverter {
+    _private: (),
+}
+
+#[cfg(target_arch = "aarch64")]
+impl CodeConverter for NEONCodeConverter {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        if cfg!(target_feature = "neon") {
+            unsafe { self.convert_impl::<false>(code, out) };
+        } else {
+            unlikely();
+            NaiveCodeConverter::default().convert(code, out);
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl TryCodeConverter for NEONCodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        if cfg!(target_feature = "neon") {
+            check_invalid(code, unsafe { self.convert_impl::<true>(code, out) })
+        } else {
+            unlikely();
+            NaiveCodeConverter::default().try_convert(code, out)
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl NEONCodeConverter {
+    /// Returns the offset of the first invalid byte if `VALIDATE` is set.
+    #[target_feature(enable = "neon")]
+    fn convert_impl<const VALIDATE: bool>(&self, code: &[u8], out: &mut [u8]) -> Option<usize> {
+        use core::arch::aarch64::*;
+
+        assert!(out.len() >= code.len());
+
+        // indexed by (byte | 0x20) - 0x60, anything outside of the table is invalid
+        static LUT: Align64<[u8; 32]> = Align64(
+            const {
+                let mut lut = [!0u8; 32];
+                lut[(b'a' - 0x60) as usize] = 0;
+                lut[(b't' - 0x60) as usize] = 1;
+                lut[(b'c' - 0x60) as usize] = 2;
+                lut[(b'g' - 0x60) as usize] = 3;
+                lut
+            },
+        );
+
+        let simd_len = code.len() / 16;
+        let (code_simd, code_rest) = code.split_at(simd_len * 16);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+        unsafe {
+            let lut = vld1q_u8_x2(LUT.0.as_ptr());
+            let tolower = vdupq_n_u8(0x20);
+            let offset = vdupq_n_u8(0x60);
+            let nil = vdupq_n_u8(!0);
+
+            for (i, (chunk_in, chunk_out)) in code_simd
+                .chunks_exact(16)
+                .zip(out_simd.chunks_exact_mut(16))
+                .enumerate()
+            {
+                let chunk = vorrq_u8(vld1q_u8(chunk_in.as_ptr()), tolower);
+                let indices = vsubq_u8(chunk, offset);
+
+                // out of range indices keep the value from `nil`
+                let result = vqtbx2q_u8(nil, lut, indices);
+                vst1q_u8(chunk_out.as_mut_ptr(), result);
+
+                // there is no movemask, so only locate the byte once the chunk is known to be bad
+                if VALIDATE && vmaxvq_u8(vceqq_u8(result, nil)) != 0 {
+                    return first_invalid(chunk_out).map(|j| i * 16 + j);
+                }
+            }
+        }
+
+        convert_rest::<VALIDATE>(simd_len * 16, code_rest, out_rest)
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+#[derive(Default)]
+pub struct Simd128CodeConverter {
+    _private: (),
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl CodeConverter for Simd128CodeConverter {
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        self.convert_impl::<false>(code, out);
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl TryCodeConverter for Simd128CodeConverter {
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        check_invalid(code, self.convert_impl::<true>(code, out))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+impl Simd128CodeConverter {
+    /// Returns the offset of the first invalid byte if `VALIDATE` is set.
+    fn convert_impl<const VALIDATE: bool>(&self, code: &[u8], out: &mut [u8]) -> Option<usize> {
+        use core::arch::wasm32::*;
+
+        assert!(out.len() >= code.len());
+
+        let simd_len = code.len() / 16;
+        let (code_simd, code_rest) = code.split_at(simd_len * 16);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 16);
+
+        unsafe {
+            let lut_0 = v128_load(ComputeLut::<0>::TABLE.0.as_ptr().cast());
+            let lut_1 = v128_load(ComputeLut::<1>::TABLE.0.as_ptr().cast());
+
+            for (i, (chunk_in, chunk_out)) in code_simd
+                .chunks_exact(16)
+                .zip(out_simd.chunks_exact_mut(16))
+                .enumerate()
+            {
+                // same as SSSE3CodeConverter, but swizzle only zeroes indices >= 16 so
+                // keep the sign bit when masking
+                let chunk =
+                    i8x16_sub_sat(v128_load(chunk_in.as_ptr().cast()), i8x16_splat(0b100_0000));
+
+                let result0 = i8x16_swizzle(lut_0, v128_and(chunk, u8x16_splat(0b1000_1111)));
+                let result1 =
+                    i8x16_swizzle(lut_1, v128_and(u8x16_shr(chunk, 1), u8x16_splat(0b1111)));
+
+                let results_mask = i8x16_eq(result0, result1);
+
+                let result = u8x16_sub(v128_and(results_mask, result0), u8x16_splat(1));
+                v128_store(chunk_out.as_mut_ptr().cast(), result);
+
+                if VALIDATE {
+                    let invalid = u8x16_bitmask(i8x16_eq(result, u8x16_splat(!0)));
+                    if invalid != 0 {
+                        return Some(i * 16 + invalid.trailing_zeros() as usize);
+                    }
+                }
             }
         }
-        let remainder = chunks.remainder();
-        let out_remainder = out_chunks.into_remainder();
 
-        NaiveCodeConverter::default().convert(remainder, out_remainder);
+        convert_rest::<VALIDATE>(simd_len * 16, code_rest, out_rest)
+    }
+}
+
+/// The kernel a dispatching converter selected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    Scalar,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    SSE2,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    SSSE3,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    AVX2,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    AVX512Vbmi,
+    #[cfg(target_arch = "aarch64")]
+    NEON,
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    Simd128,
+}
+
+impl Backend {
+    const ALL: &'static [Self] = &[
+        Self::Scalar,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Self::SSE2,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Self::SSSE3,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Self::AVX2,
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        Self::AVX512Vbmi,
+        #[cfg(target_arch = "aarch64")]
+        Self::NEON,
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        Self::Simd128,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Scalar => "scalar",
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Self::SSE2 => "sse2",
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Self::SSSE3 => "ssse3",
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Self::AVX2 => "avx2",
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Self::AVX512Vbmi => "avx512vbmi",
+            #[cfg(target_arch = "aarch64")]
+            Self::NEON => "neon",
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Self::Simd128 => "simd128",
+        }
+    }
+
+    /// Returns the fastest backend supported by the running CPU.
+    ///
+    /// The result is computed on the first call and cached afterwards.
+    pub fn detect() -> Self {
+        // 0 means not yet detected, otherwise the index into `ALL` plus one.
+        static DETECTED: AtomicU8 = AtomicU8::new(0);
+
+        match DETECTED.load(Ordering::Relaxed) {
+            0 => {
+                let backend = Self::detect_uncached();
+                let idx = Self::ALL.iter().position(|b| *b == backend).unwrap();
+                DETECTED.store(idx as u8 + 1, Ordering::Relaxed);
+                backend
+            }
+            idx => Self::ALL[idx as usize - 1],
+        }
     }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn detect_uncached() -> Self {
+        if x86_avx512vbmi::get() {
+            Self::AVX512Vbmi
+        } else if x86_avx2::get() {
+            Self::AVX2
+        } else if x86_ssse3::get() {
+            Self::SSSE3
+        } else {
+            Self::SSE2
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    fn detect_uncached() -> Self {
+        if cfg!(target_feature = "neon") {
+            Self::NEON
+        } else {
+            Self::Scalar
+        }
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    fn detect_uncached() -> Self {
+        Self::Simd128
+    }
+
+    #[cfg(not(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )))]
+    fn detect_uncached() -> Self {
+        Self::Scalar
+    }
+}
+
+impl core::fmt::Display for Backend {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+type ConvertFn = fn(&[u8], &mut [u8]);
+type TryConvertFn = fn(&[u8], &mut [u8]) -> Result<usize, InvalidBase>;
+
+fn convert_scalar(code: &[u8], out: &mut [u8]) {
+    NaiveCodeConverter::default().convert(code, out)
+}
+
+fn try_convert_scalar(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    NaiveCodeConverter::default().try_convert(code, out)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn convert_sse2(code: &[u8], out: &mut [u8]) {
+    SSE2CodeConverter::default().convert(code, out)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn try_convert_sse2(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    SSE2CodeConverter::default().try_convert(code, out)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn convert_ssse3(code: &[u8], out: &mut [u8]) {
+    // SAFETY: only selected after ssse3 was detected
+    unsafe { SSSE3CodeConverter::default().convert_impl::<false>(code, out) };
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn try_convert_ssse3(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    // SAFETY: only selected after ssse3 was detected
+    check_invalid(code, unsafe {
+        SSSE3CodeConverter::default().convert_impl::<true>(code, out)
+    })
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn convert_avx2(code: &[u8], out: &mut [u8]) {
+    // SAFETY: only selected after avx2 was detected
+    unsafe { AVX2CodeConverter::default().convert_impl::<false>(code, out) };
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn try_convert_avx2(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    // SAFETY: only selected after avx2 was detected
+    check_invalid(code, unsafe {
+        AVX2CodeConverter::default().convert_impl::<true>(code, out)
+    })
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn convert_avx512vbmi(code: &[u8], out: &mut [u8]) {
+    // SAFETY: only selected after avx512vbmi was detected
+    unsafe { AVX512VbmiCodeConverter::default().convert_impl::<false>(code, out) };
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn try_convert_avx512vbmi(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    // SAFETY: only selected after avx512vbmi was detected
+    check_invalid(code, unsafe {
+        AVX512VbmiCodeConverter::default().convert_impl::<true>(code, out)
+    })
+}
+
+#[cfg(target_arch = "aarch64")]
+fn convert_neon(code: &[u8], out: &mut [u8]) {
+    // SAFETY: only selected when neon is enabled for the target
+    unsafe { NEONCodeConverter::default().convert_impl::<false>(code, out) };
+}
+
+#[cfg(target_arch = "aarch64")]
+fn try_convert_neon(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    // SAFETY: only selected when neon is enabled for the target
+    check_invalid(code, unsafe {
+        NEONCodeConverter::default().convert_impl::<true>(code, out)
+    })
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn convert_simd128(code: &[u8], out: &mut [u8]) {
+    Simd128CodeConverter::default().convert(code, out)
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn try_convert_simd128(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    Simd128CodeConverter::default().try_convert(code, out)
+}
+
+/// A converter that picks the fastest kernel for the running CPU once at construction.
+///
+/// Unlike the per-ISA converters, no feature check is done on each call.
+#[derive(Clone, Copy)]
+pub struct AutoCodeConverter {
+    backend: Backend,
+    kernel: ConvertFn,
+    try_kernel: TryConvertFn,
+}
+
+impl AutoCodeConverter {
+    pub fn new() -> Self {
+        Self::with_backend(Backend::detect())
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        let (kernel, try_kernel): (ConvertFn, TryConvertFn) = match backend {
+            Backend::Scalar => (convert_scalar, try_convert_scalar),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::SSE2 => (convert_sse2, try_convert_sse2),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::SSSE3 => (convert_ssse3, try_convert_ssse3),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::AVX2 => (convert_avx2, try_convert_avx2),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            Backend::AVX512Vbmi => (convert_avx512vbmi, try_convert_avx512vbmi),
+            #[cfg(target_arch = "aarch64")]
+            Backend::NEON => (convert_neon, try_convert_neon),
+            #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+            Backend::Simd128 => (convert_simd128, try_convert_simd128),
+        };
+        Self {
+            backend,
+            kernel,
+            try_kernel,
+        }
+    }
+
+    /// The backend selected for this CPU.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+}
+
+impl Default for AutoCodeConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CodeConverter for AutoCodeConverter {
+    #[inline]
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        (self.kernel)(code, out)
+    }
+}
+
+impl TryCodeConverter for AutoCodeConverter {
+    #[inline]
+    fn try_convert(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        (self.try_kernel)(code, out)
+    }
+}
+
+/// Convert `code` into `out` using the fastest converter for the running CPU.
+pub fn convert(code: &[u8], out: &mut [u8]) {
+    AutoCodeConverter::new().convert(code, out)
+}
+
+/// Convert `code` into `out` using the fastest converter for the running CPU, failing on the first
+/// byte that is not a base.
+pub fn try_convert(code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+    AutoCodeConverter::new().try_convert(code, out)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::{RngCore, SeedableRng};
+    use rand::{Rng, RngCore, SeedableRng};
 
     fn test_converter<T: CodeConverter + Default>(converter: &T) {
         let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
@@ -385,12 +1050,12 @@ mod tests {
         let mut out1_buf = [0u8; 100_000];
         let mut out2_buf = [0u8; 100_000];
         for n in [1, 10, 100, 1000, 10_000, 100_000] {
-            let mut code = &mut buf[..n];
-            rng.fill_bytes(&mut code);
-            let mut out1 = &mut out1_buf[..n];
-            let mut out2 = &mut out2_buf[..n];
-            NaiveCodeConverter::default().convert(&code, &mut out1);
-            converter.convert(&code, &mut out2);
+            let code = &mut buf[..n];
+            rng.fill_bytes(code);
+            let out1 = &mut out1_buf[..n];
+            let out2 = &mut out2_buf[..n];
+            NaiveCodeConverter::default().convert(code, out1);
+            converter.convert(code, out2);
             code.iter()
                 .zip(out1.iter())
                 .zip(out2.iter())
@@ -404,12 +1069,49 @@ mod tests {
         }
     }
 
+    fn test_try_converter<T: TryCodeConverter>(converter: &T) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for n in [1, 10, 100, 1000, 10_000] {
+            let mut code: Vec<u8> = (0..n)
+                .map(|_| b"ACGTacgt"[rng.random_range(0..8)])
+                .collect();
+            let mut expected = vec![0; n];
+            let mut out = vec![0; n];
+            NaiveCodeConverter::default().convert(&code, &mut expected);
+            assert_eq!(converter.try_convert(&code, &mut out), Ok(n));
+            assert_eq!(out, expected);
+
+            for _ in 0..10 {
+                let position = rng.random_range(0..n);
+                let byte = b"Nn-\0\xff@["[rng.random_range(0..7)];
+                let saved = code[position];
+                code[position] = byte;
+                // a later invalid byte must not be reported instead
+                let later = rng.random_range(position..n);
+                let saved_later = code[later];
+                code[later] = b'X';
+                assert_eq!(
+                    converter.try_convert(&code, &mut out),
+                    Err(InvalidBase {
+                        position,
+                        byte: code[position]
+                    }),
+                    "length {}",
+                    n
+                );
+                code[later] = saved_later;
+                code[position] = saved;
+            }
+        }
+    }
+
     macro_rules! write_test {
         ($name:ident, $converter:ty) => {
             #[test]
             fn $name() {
                 let converter = <$converter>::default();
                 test_converter(&converter);
+                test_try_converter(&converter);
             }
         };
     }
@@ -417,8 +1119,73 @@ mod tests {
     write_test!(test_naive_converter, NaiveCodeConverter);
     write_test!(test_naive_to_lower_converter, NaiveToLowerCodeConverter);
     write_test!(test_lut_converter, LUTCodeConverter);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     write_test!(test_sse2_converter, SSE2CodeConverter);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     write_test!(test_ssse3_converter, SSSE3CodeConverter);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     write_test!(test_avx2_converter, AVX2CodeConverter);
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     write_test!(test_avx512vbmi_converter, AVX512VbmiCodeConverter);
+    #[cfg(target_arch = "aarch64")]
+    write_test!(test_neon_converter, NEONCodeConverter);
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    write_test!(test_simd128_converter, Simd128CodeConverter);
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_avx512vbmi_converter_unaligned() {
+        // the input is loaded without an alignment requirement
+        let code = Align64([b'G'; 129]);
+        let mut out = [0; 128];
+        AVX512VbmiCodeConverter::default().convert(&code.0[1..], &mut out);
+        assert_eq!(out, [3; 128]);
+    }
+
+    write_test!(test_auto_converter, AutoCodeConverter);
+
+    #[test]
+    fn test_auto_converter_all_backends() {
+        for &backend in Backend::ALL {
+            let supported = match backend {
+                Backend::Scalar => true,
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                Backend::SSE2 => true,
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                Backend::SSSE3 => x86_ssse3::get(),
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                Backend::AVX2 => x86_avx2::get(),
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                Backend::AVX512Vbmi => x86_avx512vbmi::get(),
+                #[cfg(target_arch = "aarch64")]
+                Backend::NEON => cfg!(target_feature = "neon"),
+                #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+                Backend::Simd128 => true,
+            };
+            if supported {
+                test_converter(&AutoCodeConverter::with_backend(backend));
+                test_try_converter(&AutoCodeConverter::with_backend(backend));
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert_fn() {
+        let mut out = [0u8; 18];
+        convert(b"ATCGatcgATCGatcgNn", &mut out);
+        assert_eq!(
+            out,
+            [0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 255, 255]
+        );
+        assert_eq!(Backend::detect(), AutoCodeConverter::new().backend());
+
+        assert_eq!(try_convert(b"ATCGatcg", &mut out), Ok(8));
+        assert_eq!(
+            try_convert(b"ATCGatcgN", &mut out),
+            Err(InvalidBase {
+                position: 8,
+                byte: b'N'
+            })
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use std::any::Any;
 
-use nucleotide_converter::{CodeConverter, CodeConverterInPlace};
+use nucleotide_converter::custom_alphabet::{PackInPlace, Unpack};
+use nucleotide_converter::{CodeConverter, two_bit::TwoBitPack};
 
 fn demo_converter<T: CodeConverter + Any>(code: &str, converter: &T) {
     let mut out = vec![0; code.len()];
@@ -8,21 +9,17 @@ fn demo_converter<T: CodeConverter + Any>(code: &str, converter: &T) {
     println!("[{}] {:?}", std::any::type_name::<T>(), out);
 }
 
-fn demo_pack_unpack<P: CodeConverterInPlace, U: CodeConverter>(
-    code: &str,
-    packer: &P,
-    unpacker: &U,
-) {
+fn demo_pack_unpack<P: PackInPlace, U: Unpack>(code: &str, packer: &P, unpacker: &U) {
     let mut packed = code.as_bytes().to_vec();
-    let packed = packer.convert_in_place(&mut packed);
+    let packed = packer.pack_in_place(&mut packed);
     let mut unpacked = vec![0; code.len()];
-    unpacker.convert(&packed, &mut unpacked);
+    unpacker.unpack(packed, &mut unpacked);
     println!(
         "[{} -> {}] packed -> {} = {:02x?}",
         std::any::type_name::<U>(),
         std::any::type_name::<P>(),
         code,
-        packed
+        packed.as_bytes()
     );
 }
 
@@ -34,13 +31,24 @@ fn main() {
     demo_converter(&code, &converter);
     let converter = nucleotide_converter::LUTCodeConverter::default();
     demo_converter(&code, &converter);
-    let converter = nucleotide_converter::SSE2CodeConverter::default();
-    demo_converter(&code, &converter);
-    let converter = nucleotide_converter::SSSE3CodeConverter::default();
-    demo_converter(&code, &converter);
-    let converter = nucleotide_converter::AVX2CodeConverter::default();
-    demo_converter(&code, &converter);
-    let converter = nucleotide_converter::AVX512VbmiCodeConverter::default();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        let converter = nucleotide_converter::SSE2CodeConverter::default();
+        demo_converter(&code, &converter);
+        let converter = nucleotide_converter::SSSE3CodeConverter::default();
+        demo_converter(&code, &converter);
+        let converter = nucleotide_converter::AVX2CodeConverter::default();
+        demo_converter(&code, &converter);
+        let converter = nucleotide_converter::AVX512VbmiCodeConverter::default();
+        demo_converter(&code, &converter);
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        let converter = nucleotide_converter::NEONCodeConverter::default();
+        demo_converter(&code, &converter);
+    }
+    let converter = nucleotide_converter::AutoCodeConverter::default();
+    print!("(backend: {}) ", converter.backend());
     demo_converter(&code, &converter);
 
     let (packer, unpacker) = (
@@ -49,9 +57,39 @@ fn main() {
     );
     demo_pack_unpack(&code, &packer, &unpacker);
 
-    let (packer, unpacker) = (
-        nucleotide_converter::custom_alphabet::SSE41InPlacePacker::default(),
-        nucleotide_converter::custom_alphabet::SSSE3Unpacker::default(),
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        let (packer, unpacker) = (
+            nucleotide_converter::custom_alphabet::SSE41InPlacePacker::default(),
+            nucleotide_converter::custom_alphabet::SSSE3Unpacker::default(),
+        );
+        demo_pack_unpack(&code, &packer, &unpacker);
+
+        let (packer, unpacker) = (
+            nucleotide_converter::custom_alphabet::AVX512VbmiInPlacePacker::default(),
+            nucleotide_converter::custom_alphabet::AVX512VbmiUnpacker::default(),
+        );
+        demo_pack_unpack(&code, &packer, &unpacker);
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        let (packer, unpacker) = (
+            nucleotide_converter::custom_alphabet::NEONInPlacePacker::default(),
+            nucleotide_converter::custom_alphabet::NEONUnpacker::default(),
+        );
+        demo_pack_unpack(&code, &packer, &unpacker);
+    }
+
+    let mut packed = vec![0; code.len().div_ceil(4)];
+    let mut mask = vec![0; code.len().div_ceil(8)];
+    nucleotide_converter::two_bit::TwoBitPacker::default().pack_masked(
+        code.as_bytes(),
+        &mut packed,
+        &mut mask,
+    );
+    println!(
+        "[two_bit] packed -> {} = {:02x?} (mask: {:02x?})",
+        code, packed, mask
     );
-    demo_pack_unpack(&code, &packer, &unpacker);
 }
@@ -0,0 +1,234 @@
+//! Nibble-packed sequences that carry their exact length in bases.
+//!
+//! Two bases are stored per byte with the first base in the low nibble, as written by the
+//! [`custom_alphabet`](crate::custom_alphabet) packers. For odd lengths the high nibble of the
+//! last byte is unused.
+
+use core::ops::{Bound, RangeBounds};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// A borrowed packed sequence.
+///
+/// A slice taken at an odd base starts in the high nibble of its first byte, see
+/// [`offset`](Self::offset).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedSlice<'a> {
+    data: &'a [u8],
+    offset: usize,
+    len: usize,
+}
+
+impl<'a> PackedSlice<'a> {
+    /// Wrap `data` holding `len` bases.
+    ///
+    /// Panics if `data` is not exactly `len.div_ceil(2)` bytes long.
+    pub const fn new(data: &'a [u8], len: usize) -> Self {
+        assert!(data.len() == len.div_ceil(2));
+        Self {
+            data,
+            offset: 0,
+            len,
+        }
+    }
+
+    /// Number of bases.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The packed bytes, including the unused low nibble of the first byte if the
+    /// [`offset`](Self::offset) is 1.
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The nibble of the first byte holding the first base, 0 for the low nibble and 1 for the high nibble.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The code of base `i`, or `None` if it is out of bounds.
+    pub const fn get(&self, i: usize) -> Option<u8> {
+        if i >= self.len {
+            return None;
+        }
+        let nibble = self.offset + i;
+        Some((self.data[nibble / 2] >> (nibble % 2 * 4)) & 0b1111)
+    }
+
+    /// The bases in `range`, without copying.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end + 1,
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.len,
+        };
+        assert!(
+            start <= end && end <= self.len,
+            "range {}..{} out of bounds for length {}",
+            start,
+            end,
+            self.len
+        );
+
+        let first = self.offset + start;
+        let last = self.offset + end;
+        Self {
+            data: &self.data[first / 2..last.div_ceil(2)],
+            offset: first % 2,
+            len: end - start,
+        }
+    }
+
+    /// Iterate over the codes of the bases.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = u8> + 'a {
+        let Self { data, offset, len } = *self;
+        (offset..offset + len).map(move |nibble| (data[nibble / 2] >> (nibble % 2 * 4)) & 0b1111)
+    }
+
+    /// Copy into an owned sequence, realigning it to start in the low nibble.
+    #[cfg(feature = "alloc")]
+    pub fn to_sequence(&self) -> PackedSequence {
+        let data = if self.offset == 0 {
+            self.data.to_vec()
+        } else {
+            (0..self.len.div_ceil(2))
+                .map(|i| self.data[i] >> 4 | self.data.get(i + 1).map_or(0, |next| next << 4))
+                .collect()
+        };
+        PackedSequence::new(data, self.len)
+    }
+}
+
+/// An owned packed sequence.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PackedSequence {
+    data: Vec<u8>,
+    len: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl PackedSequence {
+    /// Wrap `data` holding `len` bases.
+    ///
+    /// Panics if `data` is not exactly `len.div_ceil(2)` bytes long.
+    pub fn new(data: Vec<u8>, len: usize) -> Self {
+        assert_eq!(data.len(), len.div_ceil(2));
+        Self { data, len }
+    }
+
+    /// Number of bases.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The packed bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn as_slice(&self) -> PackedSlice<'_> {
+        PackedSlice::new(&self.data, self.len)
+    }
+
+    /// Returns the packed bytes, dropping the length.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The code of base `i`, or `None` if it is out of bounds.
+    pub fn get(&self, i: usize) -> Option<u8> {
+        self.as_slice().get(i)
+    }
+
+    /// The bases in `range`, without copying.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> PackedSlice<'_> {
+        self.as_slice().slice(range)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> From<&'a PackedSequence> for PackedSlice<'a> {
+    fn from(seq: &'a PackedSequence) -> Self {
+        seq.as_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_get_and_slice() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for n in [0usize, 1, 2, 3, 10, 33, 1000] {
+            let codes: Vec<u8> = (0..n).map(|_| rng.random_range(0..16)).collect();
+            let mut data = vec![0; n.div_ceil(2)];
+            for (i, code) in codes.iter().enumerate() {
+                data[i / 2] |= code << (i % 2 * 4);
+            }
+            let seq = PackedSequence::new(data, n);
+
+            assert!(seq.iter_matches(&codes));
+            assert_eq!(seq.get(n), None);
+
+            for _ in 0..100 {
+                let start = rng.random_range(0..=n);
+                let end = rng.random_range(start..=n);
+                let slice = seq.slice(start..end);
+                assert_eq!(slice.len(), end - start);
+                assert_eq!(slice.offset(), start % 2);
+                assert_eq!(slice.iter().collect::<Vec<_>>(), codes[start..end]);
+
+                // slicing a slice composes the offsets
+                if !slice.is_empty() {
+                    let inner_start = rng.random_range(0..slice.len());
+                    let inner = slice.slice(inner_start..);
+                    assert_eq!(inner.get(0), Some(codes[start + inner_start]));
+                    assert_eq!(inner.len(), end - start - inner_start);
+                }
+
+                let owned = slice.to_sequence();
+                assert_eq!(owned.as_slice().offset(), 0);
+                assert!(owned.iter_matches(&codes[start..end]));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_out_of_bounds() {
+        PackedSlice::new(&[0x21, 0x04], 3).slice(2..4);
+    }
+
+    #[cfg(feature = "alloc")]
+    impl PackedSequence {
+        fn iter_matches(&self, codes: &[u8]) -> bool {
+            (0..self.len()).all(|i| self.get(i) == Some(codes[i]))
+                && self.as_slice().iter().eq(codes.iter().copied())
+        }
+    }
+}
@@ -0,0 +1,870 @@
+//! Reverse complement without unpacking.
+//!
+//! With the [`custom_alphabet`](crate::custom_alphabet) masks A = 1, C = 2, G = 4 and T = 8 the
+//! complement of every code, ambiguity codes included, is its 4-bit reversal: R (A | G) becomes
+//! Y (C | T) and K (G | T) becomes M (A | C).
+//!
+//! ASCII sequences and the 0..3 codes emitted by the [`CodeConverter`](crate::CodeConverter)s
+//! are reverse complemented in place with [`ReverseComplement`].
+
+#[cfg(feature = "alloc")]
+use crate::packed::PackedSequence;
+use crate::packed::PackedSlice;
+
+/// The complement of every nibble mask, its 4-bit reversal.
+pub const NIBBLE_COMPLEMENT: [u8; 16] = [
+    0x0, 0x8, 0x4, 0xc, 0x2, 0xa, 0x6, 0xe, 0x1, 0x9, 0x5, 0xd, 0x3, 0xb, 0x7, 0xf,
+];
+
+/// Reverse complement of nibble-packed sequences.
+pub trait PackedReverseComplement {
+    /// Reverse the order of the bytes of `data`, swap the two nibbles of every byte and
+    /// complement them.
+    fn reverse_complement_bytes(&self, data: &mut [u8]);
+
+    /// Move every nibble of `data` down by one: the high nibble of a byte becomes its low nibble
+    /// and the low nibble of the next byte becomes its high nibble. The last high nibble is zeroed.
+    fn shift_nibbles(&self, data: &mut [u8]);
+
+    /// Reverse complement the `len` bases packed in `data`.
+    ///
+    /// Panics if `data` is not exactly `len.div_ceil(2)` bytes long.
+    fn reverse_complement_in_place<'a>(&self, data: &'a mut [u8], len: usize) -> PackedSlice<'a> {
+        assert_eq!(data.len(), len.div_ceil(2));
+        self.reverse_complement_bytes(data);
+        // the unused high nibble of the last byte is now in front of the first base
+        if len % 2 == 1 {
+            self.shift_nibbles(data);
+        }
+        PackedSlice::new(data, len)
+    }
+
+    /// Reverse complement `packed` into the first `packed.as_bytes().len()` bytes of `out`.
+    ///
+    /// Nothing is shifted, so the result starts at an odd base whenever the input does not end
+    /// on a byte boundary.
+    fn reverse_complement<'a>(
+        &self,
+        packed: PackedSlice<'_>,
+        out: &'a mut [u8],
+    ) -> PackedSlice<'a> {
+        let bytes = packed.as_bytes();
+        let out = &mut out[..bytes.len()];
+        out.copy_from_slice(bytes);
+        self.reverse_complement_bytes(out);
+
+        let start = bytes.len() * 2 - packed.offset() - packed.len();
+        PackedSlice::new(out, bytes.len() * 2).slice(start..start + packed.len())
+    }
+
+    /// Reverse complement `packed` into a new sequence starting on a byte boundary.
+    #[cfg(feature = "alloc")]
+    fn reverse_complement_to_sequence(&self, packed: PackedSlice<'_>) -> PackedSequence {
+        let len = packed.len();
+        let mut data = packed.as_bytes().to_vec();
+        self.reverse_complement_bytes(&mut data);
+        if data.len() * 2 - packed.offset() - len == 1 {
+            self.shift_nibbles(&mut data);
+        }
+        data.truncate(len.div_ceil(2));
+        if len % 2 == 1 {
+            data[len / 2] &= 0b1111;
+        }
+        PackedSequence::new(data, len)
+    }
+}
+
+fn shift_nibbles_scalar(data: &mut [u8]) {
+    for i in 0..data.len() {
+        let next = data.get(i + 1).map_or(0, |next| next << 4);
+        data[i] = data[i] >> 4 | next;
+    }
+}
+
+pub struct LUTPackedReverseComplementer {
+    lut: [u8; 16],
+}
+
+impl Default for LUTPackedReverseComplementer {
+    fn default() -> Self {
+        Self {
+            lut: NIBBLE_COMPLEMENT,
+        }
+    }
+}
+
+impl LUTPackedReverseComplementer {
+    /// A reverse complementer for a custom alphabet, `lut` maps every nibble to its complement.
+    pub const fn new(lut: [u8; 16]) -> Self {
+        Self { lut }
+    }
+
+    fn complement(&self, byte: u8) -> u8 {
+        self.lut[(byte & 0b1111) as usize] << 4 | self.lut[(byte >> 4) as usize]
+    }
+}
+
+impl PackedReverseComplement for LUTPackedReverseComplementer {
+    fn reverse_complement_bytes(&self, data: &mut [u8]) {
+        let len = data.len();
+        for i in 0..len / 2 {
+            let (front, back) = (data[i], data[len - 1 - i]);
+            data[i] = self.complement(back);
+            data[len - 1 - i] = self.complement(front);
+        }
+        if len % 2 == 1 {
+            data[len / 2] = self.complement(data[len / 2]);
+        }
+    }
+
+    fn shift_nibbles(&self, data: &mut [u8]) {
+        shift_nibbles_scalar(data);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(x86_ssse3, "ssse3");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(x86_avx2, "avx2");
+
+/// The SSSE3 reverse complementer, falls back to [`LUTPackedReverseComplementer`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[repr(align(16))]
+pub struct SSSE3PackedReverseComplementer {
+    lut: [u8; 16],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for SSSE3PackedReverseComplementer {
+    fn default() -> Self {
+        Self {
+            lut: NIBBLE_COMPLEMENT,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl SSSE3PackedReverseComplementer {
+    /// A reverse complementer for a custom alphabet, `lut` maps every nibble to its complement.
+    pub const fn new(lut: [u8; 16]) -> Self {
+        Self { lut }
+    }
+
+    #[target_feature(enable = "ssse3")]
+    fn reverse_complement_bytes_impl(&self, data: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        #[target_feature(enable = "ssse3")]
+        fn reverse_complement(v: __m128i, low: __m128i, high: __m128i) -> __m128i {
+            let reverse = _mm_setr_epi8(15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0);
+            let nibble = _mm_set1_epi8(0b1111);
+            let v = _mm_shuffle_epi8(v, reverse);
+            // the low nibble moves up and the high nibble down
+            let to_high = _mm_shuffle_epi8(high, _mm_and_si128(v, nibble));
+            let to_low = _mm_shuffle_epi8(low, _mm_and_si128(_mm_srli_epi16(v, 4), nibble));
+            _mm_or_si128(to_high, to_low)
+        }
+
+        let (mut front, mut back) = (0, data.len());
+        unsafe {
+            let low = _mm_loadu_si128(self.lut.as_ptr().cast());
+            let high = _mm_slli_epi16(low, 4);
+
+            while back - front >= 32 {
+                let ptr = data.as_mut_ptr();
+                let a = _mm_loadu_si128(ptr.add(front).cast());
+                let b = _mm_loadu_si128(ptr.add(back - 16).cast());
+                _mm_storeu_si128(ptr.add(front).cast(), reverse_complement(b, low, high));
+                _mm_storeu_si128(ptr.add(back - 16).cast(), reverse_complement(a, low, high));
+                front += 16;
+                back -= 16;
+            }
+        }
+
+        LUTPackedReverseComplementer::new(self.lut)
+            .reverse_complement_bytes(&mut data[front..back]);
+    }
+
+    #[target_feature(enable = "ssse3")]
+    fn shift_nibbles_impl(&self, data: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        let mut i = 0;
+        unsafe {
+            let (low, high) = (_mm_set1_epi8(0x0f), _mm_set1_epi8(0xf0_u8 as _));
+            // every step also reads the byte after the 16 it writes
+            while i + 17 <= data.len() {
+                let ptr = data.as_mut_ptr().add(i);
+                let this = _mm_loadu_si128(ptr.cast());
+                let next = _mm_loadu_si128(ptr.add(1).cast());
+                let shifted = _mm_or_si128(
+                    _mm_and_si128(_mm_srli_epi16(this, 4), low),
+                    _mm_and_si128(_mm_slli_epi16(next, 4), high),
+                );
+                _mm_storeu_si128(ptr.cast(), shifted);
+                i += 16;
+            }
+        }
+
+        shift_nibbles_scalar(&mut data[i..]);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl PackedReverseComplement for SSSE3PackedReverseComplementer {
+    fn reverse_complement_bytes(&self, data: &mut [u8]) {
+        if x86_ssse3::get() {
+            unsafe { self.reverse_complement_bytes_impl(data) }
+        } else {
+            LUTPackedReverseComplementer::new(self.lut).reverse_complement_bytes(data)
+        }
+    }
+
+    fn shift_nibbles(&self, data: &mut [u8]) {
+        if x86_ssse3::get() {
+            unsafe { self.shift_nibbles_impl(data) }
+        } else {
+            shift_nibbles_scalar(data)
+        }
+    }
+}
+
+/// The AVX2 reverse complementer, falls back to [`SSSE3PackedReverseComplementer`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[repr(align(16))]
+pub struct AVX2PackedReverseComplementer {
+    lut: [u8; 16],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for AVX2PackedReverseComplementer {
+    fn default() -> Self {
+        Self {
+            lut: NIBBLE_COMPLEMENT,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl AVX2PackedReverseComplementer {
+    /// A reverse complementer for a custom alphabet, `lut` maps every nibble to its complement.
+    pub const fn new(lut: [u8; 16]) -> Self {
+        Self { lut }
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn reverse_complement_bytes_impl(&self, data: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        #[target_feature(enable = "avx2")]
+        fn reverse_complement(v: __m256i, low: __m256i, high: __m256i) -> __m256i {
+            let reverse = _mm256_setr_epi8(
+                15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, //
+                15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+            );
+            let nibble = _mm256_set1_epi8(0b1111);
+            // reverse within the lanes, then swap the lanes
+            let v = _mm256_permute4x64_epi64(_mm256_shuffle_epi8(v, reverse), 0b01_00_11_10);
+            let to_high = _mm256_shuffle_epi8(high, _mm256_and_si256(v, nibble));
+            let to_low =
+                _mm256_shuffle_epi8(low, _mm256_and_si256(_mm256_srli_epi16(v, 4), nibble));
+            _mm256_or_si256(to_high, to_low)
+        }
+
+        let (mut front, mut back) = (0, data.len());
+        unsafe {
+            let low = _mm256_broadcastsi128_si256(_mm_loadu_si128(self.lut.as_ptr().cast()));
+            let high = _mm256_slli_epi16(low, 4);
+
+            while back - front >= 64 {
+                let ptr = data.as_mut_ptr();
+                let a = _mm256_loadu_si256(ptr.add(front).cast());
+                let b = _mm256_loadu_si256(ptr.add(back - 32).cast());
+                _mm256_storeu_si256(ptr.add(front).cast(), reverse_complement(b, low, high));
+                _mm256_storeu_si256(ptr.add(back - 32).cast(), reverse_complement(a, low, high));
+                front += 32;
+                back -= 32;
+            }
+        }
+
+        SSSE3PackedReverseComplementer::new(self.lut)
+            .reverse_complement_bytes_impl(&mut data[front..back]);
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn shift_nibbles_impl(&self, data: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        let mut i = 0;
+        unsafe {
+            let (low, high) = (_mm256_set1_epi8(0x0f), _mm256_set1_epi8(0xf0_u8 as _));
+            while i + 33 <= data.len() {
+                let ptr = data.as_mut_ptr().add(i);
+                let this = _mm256_loadu_si256(ptr.cast());
+                let next = _mm256_loadu_si256(ptr.add(1).cast());
+                let shifted = _mm256_or_si256(
+                    _mm256_and_si256(_mm256_srli_epi16(this, 4), low),
+                    _mm256_and_si256(_mm256_slli_epi16(next, 4), high),
+                );
+                _mm256_storeu_si256(ptr.cast(), shifted);
+                i += 32;
+            }
+        }
+
+        SSSE3PackedReverseComplementer::new(self.lut).shift_nibbles_impl(&mut data[i..]);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl PackedReverseComplement for AVX2PackedReverseComplementer {
+    fn reverse_complement_bytes(&self, data: &mut [u8]) {
+        if x86_avx2::get() {
+            unsafe { self.reverse_complement_bytes_impl(data) }
+        } else {
+            SSSE3PackedReverseComplementer::new(self.lut).reverse_complement_bytes(data)
+        }
+    }
+
+    fn shift_nibbles(&self, data: &mut [u8]) {
+        if x86_avx2::get() {
+            unsafe { self.shift_nibbles_impl(data) }
+        } else {
+            SSSE3PackedReverseComplementer::new(self.lut).shift_nibbles(data)
+        }
+    }
+}
+
+/// The complement of every ASCII byte. IUPAC codes in either case map to their complement
+/// keeping the case, U maps to A and every other byte is left unchanged.
+pub const ASCII_COMPLEMENT: [u8; 256] = {
+    let mut lut = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        lut[i] = i as u8;
+        i += 1;
+    }
+
+    let pairs = [b"AT", b"CG", b"RY", b"KM", b"BV", b"DH"];
+    let mut i = 0;
+    while i < pairs.len() {
+        let [a, b] = *pairs[i];
+        lut[a as usize] = b;
+        lut[b as usize] = a;
+        lut[(a | 0x20) as usize] = b | 0x20;
+        lut[(b | 0x20) as usize] = a | 0x20;
+        i += 1;
+    }
+    lut[b'U' as usize] = b'A';
+    lut[b'u' as usize] = b'a';
+    lut
+};
+
+/// The complement of every 2-bit code, A = 0 and T = 1, C = 2 and G = 3 flip their lowest bit.
+/// Every other byte, such as the `255` written for invalid bases, is left unchanged.
+pub const CODE_COMPLEMENT: [u8; 256] = {
+    let mut lut = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        lut[i] = if i < 4 { i as u8 ^ 1 } else { i as u8 };
+        i += 1;
+    }
+    lut
+};
+
+/// In-place reverse complement of one byte per base sequences.
+pub trait ReverseComplement {
+    fn reverse_complement_in_place(&self, seq: &mut [u8]);
+}
+
+pub struct LUTReverseComplementer {
+    lut: [u8; 256],
+}
+
+impl Default for LUTReverseComplementer {
+    /// A reverse complementer for ASCII input.
+    fn default() -> Self {
+        Self {
+            lut: ASCII_COMPLEMENT,
+        }
+    }
+}
+
+impl LUTReverseComplementer {
+    /// A reverse complementer mapping every byte to `lut[byte]`.
+    pub const fn new(lut: [u8; 256]) -> Self {
+        Self { lut }
+    }
+
+    /// A reverse complementer for the 0..3 codes emitted by the [`CodeConverter`](crate::CodeConverter)s.
+    pub const fn from_codes() -> Self {
+        Self {
+            lut: CODE_COMPLEMENT,
+        }
+    }
+}
+
+impl ReverseComplement for LUTReverseComplementer {
+    fn reverse_complement_in_place(&self, seq: &mut [u8]) {
+        seq.reverse();
+        for base in seq {
+            *base = self.lut[*base as usize];
+        }
+    }
+}
+
+/// The SSSE3 ASCII or code reverse complementer, falls back to [`LUTReverseComplementer`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub struct SSSE3ReverseComplementer {
+    from_codes: bool,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for SSSE3ReverseComplementer {
+    /// A reverse complementer for ASCII input.
+    fn default() -> Self {
+        Self { from_codes: false }
+    }
+}
+
+/// The complement of the 5 low bits of every letter, indexed by the same bits.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const LETTER_COMPLEMENT: [u8; 32] = {
+    let mut lut = [0; 32];
+    let mut i = 0;
+    while i < 32 {
+        lut[i] = ASCII_COMPLEMENT[0x40 + i] & 0b1_1111;
+        i += 1;
+    }
+    lut
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl SSSE3ReverseComplementer {
+    /// A reverse complementer for the 0..3 codes emitted by the [`CodeConverter`](crate::CodeConverter)s.
+    pub fn from_codes() -> Self {
+        Self { from_codes: true }
+    }
+
+    fn scalar(&self) -> LUTReverseComplementer {
+        if self.from_codes {
+            LUTReverseComplementer::from_codes()
+        } else {
+            LUTReverseComplementer::default()
+        }
+    }
+
+    #[target_feature(enable = "ssse3")]
+    fn reverse_complement_impl(&self, seq: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        #[target_feature(enable = "ssse3")]
+        fn complement_ascii(v: __m128i, low: __m128i, high: __m128i) -> __m128i {
+            let letter = _mm_sub_epi8(
+                _mm_or_si128(v, _mm_set1_epi8(0x20)),
+                _mm_set1_epi8(b'a' as _),
+            );
+            let is_letter = _mm_cmpeq_epi8(_mm_min_epu8(letter, _mm_set1_epi8(25)), letter);
+
+            // the shuffles only look at the low 4 bits, bit 4 picks the table
+            let index = _mm_and_si128(v, _mm_set1_epi8(0b1_1111));
+            let bit4 = _mm_set1_epi8(0b1_0000);
+            let is_high = _mm_cmpeq_epi8(_mm_and_si128(v, bit4), bit4);
+            let complement = _mm_or_si128(
+                _mm_andnot_si128(is_high, _mm_shuffle_epi8(low, index)),
+                _mm_and_si128(is_high, _mm_shuffle_epi8(high, index)),
+            );
+            // keep the case bits
+            let complement = _mm_or_si128(complement, _mm_andnot_si128(_mm_set1_epi8(0b1_1111), v));
+            _mm_or_si128(
+                _mm_and_si128(is_letter, complement),
+                _mm_andnot_si128(is_letter, v),
+            )
+        }
+
+        #[target_feature(enable = "ssse3")]
+        fn complement_codes(v: __m128i) -> __m128i {
+            let three = _mm_set1_epi8(3);
+            let is_code = _mm_cmpeq_epi8(_mm_min_epu8(v, three), v);
+            _mm_xor_si128(v, _mm_and_si128(is_code, _mm_set1_epi8(1)))
+        }
+
+        let (mut front, mut back) = (0, seq.len());
+        unsafe {
+            let reverse = _mm_setr_epi8(15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0);
+            let low = _mm_loadu_si128(LETTER_COMPLEMENT.as_ptr().cast());
+            let high = _mm_loadu_si128(LETTER_COMPLEMENT[16..].as_ptr().cast());
+            let reverse_complement = |v| {
+                let v = _mm_shuffle_epi8(v, reverse);
+                if self.from_codes {
+                    complement_codes(v)
+                } else {
+                    complement_ascii(v, low, high)
+                }
+            };
+
+            while back - front >= 32 {
+                let ptr = seq.as_mut_ptr();
+                let a = _mm_loadu_si128(ptr.add(front).cast());
+                let b = _mm_loadu_si128(ptr.add(back - 16).cast());
+                _mm_storeu_si128(ptr.add(front).cast(), reverse_complement(b));
+                _mm_storeu_si128(ptr.add(back - 16).cast(), reverse_complement(a));
+                front += 16;
+                back -= 16;
+            }
+        }
+
+        self.scalar()
+            .reverse_complement_in_place(&mut seq[front..back]);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl ReverseComplement for SSSE3ReverseComplementer {
+    fn reverse_complement_in_place(&self, seq: &mut [u8]) {
+        if x86_ssse3::get() {
+            unsafe { self.reverse_complement_impl(seq) }
+        } else {
+            self.scalar().reverse_complement_in_place(seq)
+        }
+    }
+}
+
+/// The AVX2 ASCII or code reverse complementer, falls back to [`SSSE3ReverseComplementer`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub struct AVX2ReverseComplementer {
+    from_codes: bool,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for AVX2ReverseComplementer {
+    /// A reverse complementer for ASCII input.
+    fn default() -> Self {
+        Self { from_codes: false }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl AVX2ReverseComplementer {
+    /// A reverse complementer for the 0..3 codes emitted by the [`CodeConverter`](crate::CodeConverter)s.
+    pub fn from_codes() -> Self {
+        Self { from_codes: true }
+    }
+
+    fn ssse3(&self) -> SSSE3ReverseComplementer {
+        SSSE3ReverseComplementer {
+            from_codes: self.from_codes,
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn reverse_complement_impl(&self, seq: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        #[target_feature(enable = "avx2")]
+        fn complement_ascii(v: __m256i, low: __m256i, high: __m256i) -> __m256i {
+            let letter = _mm256_sub_epi8(
+                _mm256_or_si256(v, _mm256_set1_epi8(0x20)),
+                _mm256_set1_epi8(b'a' as _),
+            );
+            let is_letter =
+                _mm256_cmpeq_epi8(_mm256_min_epu8(letter, _mm256_set1_epi8(25)), letter);
+
+            let index = _mm256_and_si256(v, _mm256_set1_epi8(0b1_1111));
+            let bit4 = _mm256_set1_epi8(0b1_0000);
+            let is_high = _mm256_cmpeq_epi8(_mm256_and_si256(v, bit4), bit4);
+            let complement = _mm256_blendv_epi8(
+                _mm256_shuffle_epi8(low, index),
+                _mm256_shuffle_epi8(high, index),
+                is_high,
+            );
+            let complement = _mm256_or_si256(
+                complement,
+                _mm256_andnot_si256(_mm256_set1_epi8(0b1_1111), v),
+            );
+            _mm256_blendv_epi8(v, complement, is_letter)
+        }
+
+        #[target_feature(enable = "avx2")]
+        fn complement_codes(v: __m256i) -> __m256i {
+            let three = _mm256_set1_epi8(3);
+            let is_code = _mm256_cmpeq_epi8(_mm256_min_epu8(v, three), v);
+            _mm256_xor_si256(v, _mm256_and_si256(is_code, _mm256_set1_epi8(1)))
+        }
+
+        let (mut front, mut back) = (0, seq.len());
+        unsafe {
+            let reverse = _mm256_setr_epi8(
+                15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, //
+                15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+            );
+            let low =
+                _mm256_broadcastsi128_si256(_mm_loadu_si128(LETTER_COMPLEMENT.as_ptr().cast()));
+            let high = _mm256_broadcastsi128_si256(_mm_loadu_si128(
+                LETTER_COMPLEMENT[16..].as_ptr().cast(),
+            ));
+            let reverse_complement = |v| {
+                let v = _mm256_permute4x64_epi64(_mm256_shuffle_epi8(v, reverse), 0b01_00_11_10);
+                if self.from_codes {
+                    complement_codes(v)
+                } else {
+                    complement_ascii(v, low, high)
+                }
+            };
+
+            while back - front >= 64 {
+                let ptr = seq.as_mut_ptr();
+                let a = _mm256_loadu_si256(ptr.add(front).cast());
+                let b = _mm256_loadu_si256(ptr.add(back - 32).cast());
+                _mm256_storeu_si256(ptr.add(front).cast(), reverse_complement(b));
+                _mm256_storeu_si256(ptr.add(back - 32).cast(), reverse_complement(a));
+                front += 32;
+                back -= 32;
+            }
+        }
+
+        self.ssse3().reverse_complement_impl(&mut seq[front..back]);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl ReverseComplement for AVX2ReverseComplementer {
+    fn reverse_complement_in_place(&self, seq: &mut [u8]) {
+        if x86_avx2::get() {
+            unsafe { self.reverse_complement_impl(seq) }
+        } else {
+            self.ssse3().reverse_complement_in_place(seq)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_alphabet::{A, BASE_MASK_TO_CHAR, C, G, K, M, N, R, T, Y};
+    use rand::{Rng, SeedableRng};
+
+    const LENGTHS: [usize; 12] = [0, 1, 2, 3, 15, 31, 32, 33, 63, 64, 65, 1001];
+
+    fn pack(codes: &[u8]) -> ([u8; 1024], usize) {
+        let mut data = [0; 1024];
+        for (i, code) in codes.iter().enumerate() {
+            data[i / 2] |= code << (i % 2 * 4);
+        }
+        (data, codes.len())
+    }
+
+    fn reverse_complement_reference(codes: &[u8]) -> impl Iterator<Item = u8> + '_ {
+        codes
+            .iter()
+            .rev()
+            .map(|code| NIBBLE_COMPLEMENT[*code as usize])
+    }
+
+    fn test_reverse_complementer<R: PackedReverseComplement>(rc: &R) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let mut codes = [0; 2048];
+        for n in LENGTHS.into_iter().flat_map(|n| [n, 2 * n]) {
+            let codes = &mut codes[..n];
+            codes
+                .iter_mut()
+                .for_each(|code| *code = rng.random_range(0..16));
+            let (data, len) = pack(codes);
+            let data = &data[..len.div_ceil(2)];
+            let packed = PackedSlice::new(data, len);
+
+            let mut in_place = [0; 1024];
+            let in_place = &mut in_place[..data.len()];
+            in_place.copy_from_slice(data);
+            let result = rc.reverse_complement_in_place(in_place, len);
+            assert!(
+                result.iter().eq(reverse_complement_reference(codes)),
+                "in place mismatch for length {}",
+                len
+            );
+            if len % 2 == 1 {
+                assert_eq!(result.as_bytes()[len / 2] >> 4, 0);
+            }
+
+            for start in [0, 1, len / 3] {
+                let start = start.min(len);
+                let slice = packed.slice(start..);
+                let mut out = [0; 1024];
+                let result = rc.reverse_complement(slice, &mut out);
+                assert!(
+                    result
+                        .iter()
+                        .eq(reverse_complement_reference(&codes[start..])),
+                    "mismatch for {}..{}",
+                    start,
+                    len
+                );
+
+                #[cfg(feature = "alloc")]
+                {
+                    let seq = rc.reverse_complement_to_sequence(slice);
+                    assert_eq!(seq.as_slice().iter().collect::<alloc::vec::Vec<_>>(), {
+                        reverse_complement_reference(&codes[start..])
+                            .collect::<alloc::vec::Vec<_>>()
+                    });
+                    assert_eq!(
+                        rc.reverse_complement_to_sequence(seq.as_slice()),
+                        slice.to_sequence()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_complement_is_reversal() {
+        for (code, complement) in [(A, T), (C, G), (R, Y), (K, M), (N, N)] {
+            assert_eq!(NIBBLE_COMPLEMENT[code as usize], complement);
+            assert_eq!(NIBBLE_COMPLEMENT[complement as usize], code);
+        }
+
+        let rc = LUTPackedReverseComplementer::default();
+        // ACGRK -> MYCGT
+        let (mut data, len) = pack(&[A, C, G, R, K]);
+        let result = rc.reverse_complement_in_place(&mut data[..3], len);
+        let chars: [u8; 5] =
+            core::array::from_fn(|i| BASE_MASK_TO_CHAR.0[result.get(i).unwrap() as usize]);
+        assert_eq!(&chars, b"MYCGT");
+    }
+
+    #[test]
+    fn test_lut_reverse_complement() {
+        test_reverse_complementer(&LUTPackedReverseComplementer::default());
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_ssse3_reverse_complement() {
+        test_reverse_complementer(&SSSE3PackedReverseComplementer::default());
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_avx2_reverse_complement() {
+        test_reverse_complementer(&AVX2PackedReverseComplementer::default());
+    }
+
+    fn reverse_complement_ascii_reference(seq: &[u8]) -> Vec<u8> {
+        seq.iter()
+            .rev()
+            .map(|&base| {
+                let complement = match base.to_ascii_uppercase() {
+                    b'A' => b'T',
+                    b'T' | b'U' => b'A',
+                    b'C' => b'G',
+                    b'G' => b'C',
+                    b'R' => b'Y',
+                    b'Y' => b'R',
+                    b'K' => b'M',
+                    b'M' => b'K',
+                    b'B' => b'V',
+                    b'V' => b'B',
+                    b'D' => b'H',
+                    b'H' => b'D',
+                    _ => return base,
+                };
+                if base.is_ascii_lowercase() {
+                    complement.to_ascii_lowercase()
+                } else {
+                    complement
+                }
+            })
+            .collect()
+    }
+
+    fn reverse_complement_codes_reference(seq: &[u8]) -> Vec<u8> {
+        seq.iter()
+            .rev()
+            .map(|&code| match code {
+                0 => 1,
+                1 => 0,
+                2 => 3,
+                3 => 2,
+                _ => code,
+            })
+            .collect()
+    }
+
+    fn test_ascii_reverse_complementer<R: ReverseComplement>(ascii: &R, codes: &R) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for _ in 0..200 {
+            let n = rng.random_range(0..300);
+            let seq: Vec<u8> = (0..n).map(|_| rng.random()).collect();
+            let mut out = seq.clone();
+            ascii.reverse_complement_in_place(&mut out);
+            assert_eq!(
+                out,
+                reverse_complement_ascii_reference(&seq),
+                "length {}",
+                n
+            );
+
+            let seq: Vec<u8> = (0..n)
+                .map(|_| match rng.random_range(0..20) {
+                    0 => 255,
+                    _ => rng.random_range(0..4),
+                })
+                .collect();
+            let mut out = seq.clone();
+            codes.reverse_complement_in_place(&mut out);
+            assert_eq!(
+                out,
+                reverse_complement_codes_reference(&seq),
+                "length {}",
+                n
+            );
+        }
+
+        let mut seq = *b"ACGTUacgtuNnRYKMSWBDHVrykmswbdhv-*.";
+        ascii.reverse_complement_in_place(&mut seq);
+        assert_eq!(&seq, b".*-bdhvwskmryBDHVWSKMRYnNaacgtAACGT");
+    }
+
+    #[test]
+    fn test_lut_ascii_reverse_complement() {
+        test_ascii_reverse_complementer(
+            &LUTReverseComplementer::default(),
+            &LUTReverseComplementer::from_codes(),
+        );
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_ssse3_ascii_reverse_complement() {
+        test_ascii_reverse_complementer(
+            &SSSE3ReverseComplementer::default(),
+            &SSSE3ReverseComplementer::from_codes(),
+        );
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_avx2_ascii_reverse_complement() {
+        test_ascii_reverse_complementer(
+            &AVX2ReverseComplementer::default(),
+            &AVX2ReverseComplementer::from_codes(),
+        );
+    }
+}
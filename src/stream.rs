@@ -0,0 +1,412 @@
+//! [`Read`] and [`Write`] adapters that convert or pack bytes as they stream through.
+//!
+//! The writers skip line endings, so a FASTA body can be copied in with [`io::copy`] as it is.
+//! Every adapter works through an internal buffer of [`BUF_SIZE`] bases, large enough for the
+//! SIMD kernels to run on whole vectors whatever the size of the caller's reads and writes.
+//!
+//! ```
+//! use std::io::{self, Read};
+//!
+//! use nucleotide_converter::custom_alphabet::{LUTPacker, LUTUnpacker};
+//! use nucleotide_converter::stream::{PackingWriter, UnpackingReader};
+//!
+//! let body = b"ACGTN\nacgta\nCC\n";
+//! let mut writer = PackingWriter::new(Vec::new(), LUTPacker::default());
+//! io::copy(&mut &body[..], &mut writer)?;
+//! let packed = writer.finish()?;
+//! assert_eq!(packed.len(), 6);
+//!
+//! let mut seq = Vec::new();
+//! UnpackingReader::with_len(&packed[..], LUTUnpacker::default(), 12).read_to_end(&mut seq)?;
+//! assert_eq!(seq, b"ACGTNACGTACC");
+//! # Ok::<(), io::Error>(())
+//! ```
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::CodeConverter;
+use crate::custom_alphabet::{Pack, StreamingPacker, Unpack};
+use crate::fasta::strip_newlines;
+
+/// Number of bases the adapters convert at a time.
+pub const BUF_SIZE: usize = 64 * 1024;
+
+/// Packs the bases written to it into `writer`.
+///
+/// [`finish`](Self::finish) must be called to write the last base of an odd-length sequence.
+pub struct PackingWriter<W, P> {
+    writer: W,
+    packer: StreamingPacker<P>,
+    bases: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write, P: Pack> PackingWriter<W, P> {
+    pub fn new(writer: W, packer: P) -> Self {
+        Self {
+            writer,
+            packer: StreamingPacker::new(packer),
+            bases: Vec::with_capacity(BUF_SIZE),
+            buf: vec![0; BUF_SIZE / 2 + 1],
+        }
+    }
+
+    /// Number of bases written so far.
+    pub fn len(&self) -> usize {
+        self.packer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packer.is_empty()
+    }
+
+    /// Write the pending base, if any, flush and return the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let len = self.packer.finish(&mut self.buf);
+        self.writer.write_all(&self.buf[..len])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write, P: Pack> Write for PackingWriter<W, P> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        for chunk in data.chunks(BUF_SIZE) {
+            self.bases.clear();
+            strip_newlines(chunk, &mut self.bases);
+            let len = self.packer.push(&self.bases, &mut self.buf);
+            self.writer.write_all(&self.buf[..len])?;
+        }
+        Ok(data.len())
+    }
+
+    /// Flushes the inner writer. A pending base is only written by [`finish`](Self::finish).
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Unpacks a packed sequence read from `reader` into ASCII.
+pub struct UnpackingReader<R, U> {
+    reader: R,
+    unpacker: U,
+    packed: Vec<u8>,
+    /// The last byte read, held back until it is known whether it is the last of the input.
+    held: Option<u8>,
+    /// Bases left to read, if the length is known.
+    remaining: Option<u64>,
+    out: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read, U: Unpack> UnpackingReader<R, U> {
+    /// A reader that guesses the length like the [`CodeConverter`] impls of the unpackers: the
+    /// last byte holds a single base if its high nibble is zero.
+    pub fn new(reader: R, unpacker: U) -> Self {
+        Self {
+            reader,
+            unpacker,
+            packed: vec![0; BUF_SIZE / 2],
+            held: None,
+            remaining: None,
+            out: Vec::with_capacity(BUF_SIZE),
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// A reader that unpacks exactly `len` bases and reads no further than their
+    /// `len.div_ceil(2)` bytes.
+    pub fn with_len(reader: R, unpacker: U, len: u64) -> Self {
+        Self {
+            remaining: Some(len),
+            ..Self::new(reader, unpacker)
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Unpack the next bytes of the input into `self.out`, which may stay empty.
+    fn refill(&mut self) -> io::Result<()> {
+        self.out.clear();
+        self.pos = 0;
+
+        let start = self.held.is_some() as usize;
+        let mut limit = self.packed.len();
+        if let Some(remaining) = self.remaining {
+            limit = limit.min(remaining.div_ceil(2) as usize);
+        }
+        let read = loop {
+            match self.reader.read(&mut self.packed[start..limit]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        };
+        if let Some(held) = self.held.take() {
+            self.packed[0] = held;
+        }
+        let mut len = start + read;
+        let eof = read == 0;
+        if !eof && self.remaining.is_none() {
+            self.held = Some(self.packed[len - 1]);
+            len -= 1;
+        }
+
+        self.out.resize(len * 2, 0);
+        self.unpacker
+            .unpack_bytes(&self.packed[..len], &mut self.out);
+        match &mut self.remaining {
+            Some(remaining) => {
+                if eof && *remaining > 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                self.out.truncate((*remaining).min(len as u64 * 2) as usize);
+                *remaining -= self.out.len() as u64;
+                self.done = *remaining == 0;
+            }
+            None if eof => {
+                if self.packed[..len]
+                    .last()
+                    .is_some_and(|last| last & 0xf0 == 0)
+                {
+                    self.out.pop();
+                }
+                self.done = true;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, U: Unpack> Read for UnpackingReader<R, U> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read, U: Unpack> BufRead for UnpackingReader<R, U> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.out.len() && !self.done {
+            self.refill()?;
+        }
+        Ok(&self.out[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.out.len());
+    }
+}
+
+/// Converts the bases written to it with a [`CodeConverter`] that writes one byte per base.
+pub struct ConvertingWriter<W, C> {
+    writer: W,
+    converter: C,
+    bases: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write, C: CodeConverter> ConvertingWriter<W, C> {
+    pub fn new(writer: W, converter: C) -> Self {
+        Self {
+            writer,
+            converter,
+            bases: Vec::with_capacity(BUF_SIZE),
+            buf: vec![0; BUF_SIZE],
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, C: CodeConverter> Write for ConvertingWriter<W, C> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        for chunk in data.chunks(BUF_SIZE) {
+            self.bases.clear();
+            strip_newlines(chunk, &mut self.bases);
+            let out = &mut self.buf[..self.bases.len()];
+            self.converter.convert(&self.bases, out);
+            self.writer.write_all(out)?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Converts the bytes read from `reader` with a [`CodeConverter`] that writes one byte per base.
+pub struct ConvertingReader<R, C> {
+    reader: R,
+    converter: C,
+    buf: Vec<u8>,
+}
+
+impl<R: Read, C: CodeConverter> ConvertingReader<R, C> {
+    pub fn new(reader: R, converter: C) -> Self {
+        Self {
+            reader,
+            converter,
+            buf: vec![0; BUF_SIZE],
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read, C: CodeConverter> Read for ConvertingReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(BUF_SIZE);
+        let len = self.reader.read(&mut self.buf[..len])?;
+        self.converter.convert(&self.buf[..len], &mut buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NaiveCodeConverter;
+    use crate::custom_alphabet::{LUTPacker, LUTUnpacker};
+    use crate::packed::PackedSlice;
+    use rand::{Rng, SeedableRng};
+
+    /// A reader returning at most `chunk` bytes per read, to cut the input at odd places.
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.chunk).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    fn random_body(rng: &mut impl Rng, len: usize) -> Vec<u8> {
+        let mut body = Vec::new();
+        for i in 0..len {
+            body.push(b"ACGTNacgtn"[rng.random_range(0..10)]);
+            if i % 60 == 59 {
+                body.push(b'\n');
+            }
+        }
+        body.push(b'\n');
+        body
+    }
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for len in [0, 1, 2, 59, 60, 61, 1001, 200_001] {
+            let body = random_body(&mut rng, len);
+            let mut seq = Vec::new();
+            strip_newlines(&body, &mut seq);
+            seq.make_ascii_uppercase();
+
+            let mut writer = PackingWriter::new(Vec::new(), LUTPacker::default());
+            io::copy(
+                &mut Chunked {
+                    data: &body,
+                    chunk: 33,
+                },
+                &mut writer,
+            )
+            .unwrap();
+            assert_eq!(writer.len(), len);
+            let packed = writer.finish().unwrap();
+            let mut expected = vec![0; len.div_ceil(2)];
+            LUTPacker::default().convert(&seq, &mut expected);
+            assert_eq!(packed, expected, "length {}", len);
+
+            for chunk in [1, 7, BUF_SIZE] {
+                let mut unpacked = Vec::new();
+                let reader = Chunked {
+                    data: &packed,
+                    chunk,
+                };
+                io::copy(
+                    &mut UnpackingReader::with_len(reader, LUTUnpacker::default(), len as u64),
+                    &mut unpacked,
+                )
+                .unwrap();
+                assert_eq!(unpacked, seq, "length {}", len);
+            }
+
+            // no base packs to 0, so the length can be guessed
+            let mut unpacked = Vec::new();
+            let reader = Chunked {
+                data: &packed,
+                chunk: 5,
+            };
+            UnpackingReader::new(reader, LUTUnpacker::default())
+                .read_to_end(&mut unpacked)
+                .unwrap();
+            assert_eq!(unpacked, seq, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_unpacking_reader_stops_at_len() {
+        let packed = [0x21, 0x84, 0x01, 0xff];
+        let mut reader = UnpackingReader::with_len(&packed[..], LUTUnpacker::default(), 5);
+        let mut seq = Vec::new();
+        reader.read_to_end(&mut seq).unwrap();
+        assert_eq!(seq, b"ACGTA");
+        assert_eq!(reader.into_inner(), [0xff]);
+
+        let mut reader = UnpackingReader::with_len(&packed[..2], LUTUnpacker::default(), 5);
+        let err = reader.read_to_end(&mut seq).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut seq = vec![0; 5];
+        let mut reader = UnpackingReader::new(&packed[..3], LUTUnpacker::default());
+        reader.read_exact(&mut seq).unwrap();
+        assert_eq!(
+            seq,
+            LUTUnpacker::default().unpack_to_vec(PackedSlice::new(&packed[..3], 5))
+        );
+        assert_eq!(reader.read(&mut seq).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_converting_adapters() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let body = random_body(&mut rng, 100_001);
+        let mut seq = Vec::new();
+        strip_newlines(&body, &mut seq);
+        let mut expected = vec![0; seq.len()];
+        NaiveCodeConverter::default().convert(&seq, &mut expected);
+
+        let mut writer = ConvertingWriter::new(Vec::new(), NaiveCodeConverter::default());
+        io::copy(&mut &body[..], &mut writer).unwrap();
+        assert_eq!(writer.into_inner(), expected);
+
+        let mut converted = Vec::new();
+        let mut reader = ConvertingReader::new(
+            Chunked {
+                data: &seq,
+                chunk: 1000,
+            },
+            NaiveCodeConverter::default(),
+        );
+        io::copy(&mut reader, &mut converted).unwrap();
+        assert_eq!(converted, expected);
+    }
+}
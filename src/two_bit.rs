@@ -0,0 +1,799 @@
+//! Dense 2-bit packing, 4 bases per byte.
+//!
+//! Codes follow [`NaiveCodeConverter`](crate::NaiveCodeConverter): A = 0, T = 1, C = 2, G = 3.
+//! The first base goes into the lowest two bits of a byte.
+
+use crate::{CodeConverter, InvalidBase};
+#[cfg(feature = "alloc")]
+use {
+    crate::custom_alphabet::{BASE_CHAR_TO_MASK, BASE_MASK_TO_CHAR},
+    alloc::{vec, vec::Vec},
+};
+
+/// LUT for converting an ASCII character to a 2-bit code, anything else is `!0`.
+static ASCII_TO_CODE: [u8; 256] = const {
+    let mut table = [!0u8; 256];
+    table[b'A' as usize] = 0;
+    table[b'T' as usize] = 1;
+    table[b'C' as usize] = 2;
+    table[b'G' as usize] = 3;
+    table[b'a' as usize] = 0;
+    table[b't' as usize] = 1;
+    table[b'c' as usize] = 2;
+    table[b'g' as usize] = 3;
+    table
+};
+
+/// LUT accepting codes that are already 2-bit, anything else is `!0`.
+static CODE_TO_CODE: [u8; 256] = const {
+    let mut table = [!0u8; 256];
+    table[0] = 0;
+    table[1] = 1;
+    table[2] = 2;
+    table[3] = 3;
+    table
+};
+
+/// Number of bases checked at once when looking for invalid bytes.
+const BLOCK: usize = 4096;
+
+/// A packer from ASCII or 2-bit codes to 4 bases per byte.
+///
+/// Bytes that are not bases are handled according to the method used:
+/// [`try_pack`](Self::try_pack) fails, [`pack_substitute`](Self::pack_substitute) replaces them
+/// and [`pack_masked`](Self::pack_masked) records them in a side mask.
+pub trait TwoBitPack {
+    /// Pack `code` into `out`, writing `substitute` for bytes that are not bases.
+    ///
+    /// If `mask` is given, bit `i` (LSB first) is set if `code[i]` is not a base and cleared otherwise.
+    fn pack_raw(&self, code: &[u8], out: &mut [u8], mask: Option<&mut [u8]>, substitute: u8);
+
+    /// Pack `code` into `out`, failing on the first byte that is not a base.
+    ///
+    /// Returns the number of bytes written.
+    fn try_pack(&self, code: &[u8], out: &mut [u8]) -> Result<usize, InvalidBase> {
+        let packed_len = code.len().div_ceil(4);
+        assert!(out.len() >= packed_len);
+
+        let mut mask = [0u8; BLOCK / 8];
+        for (i, (block, out)) in code
+            .chunks(BLOCK)
+            .zip(out.chunks_mut(BLOCK / 4))
+            .enumerate()
+        {
+            self.pack_raw(block, out, Some(&mut mask), 0);
+
+            if let Some((idx, bits)) = mask[..block.len().div_ceil(8)]
+                .iter()
+                .enumerate()
+                .find(|(_, bits)| **bits != 0)
+            {
+                let position = i * BLOCK + idx * 8 + bits.trailing_zeros() as usize;
+                return Err(InvalidBase {
+                    position,
+                    byte: code[position],
+                });
+            }
+        }
+
+        Ok(packed_len)
+    }
+
+    /// Pack `code` into `out`, replacing bytes that are not bases with `substitute`.
+    ///
+    /// Returns the number of bytes written.
+    fn pack_substitute(&self, code: &[u8], out: &mut [u8], substitute: u8) -> usize {
+        assert!(substitute < 4);
+        self.pack_raw(code, out, None, substitute);
+        code.len().div_ceil(4)
+    }
+
+    /// Pack `code` into `out`, writing code 0 for bytes that are not bases and setting their bit in `mask`.
+    ///
+    /// Returns the number of bytes written. Use [`apply_mask`] to restore them after unpacking.
+    fn pack_masked(&self, code: &[u8], out: &mut [u8], mask: &mut [u8]) -> usize {
+        self.pack_raw(code, out, Some(mask), 0);
+        code.len().div_ceil(4)
+    }
+}
+
+/// Overwrite every base whose bit is set in `mask` with `fill`.
+pub fn apply_mask(mask: &[u8], out: &mut [u8], fill: u8) {
+    assert!(mask.len() >= out.len().div_ceil(8));
+
+    for (bits, chunk) in mask.iter().zip(out.chunks_mut(8)) {
+        let mut bits = *bits;
+        while bits != 0 {
+            let idx = bits.trailing_zeros() as usize;
+            if let Some(b) = chunk.get_mut(idx) {
+                *b = fill;
+            }
+            bits &= bits - 1;
+        }
+    }
+}
+
+#[inline(always)]
+fn pack_scalar(
+    lut: &[u8; 256],
+    code: &[u8],
+    out: &mut [u8],
+    mask: Option<&mut [u8]>,
+    substitute: u8,
+) {
+    assert!(out.len() >= code.len().div_ceil(4));
+
+    let mut invalid = 0u8;
+    let mut mask = mask;
+    for (i, (chunk, out)) in code.chunks(4).zip(out.iter_mut()).enumerate() {
+        let mut byte = 0;
+        for (j, c) in chunk.iter().enumerate() {
+            let mut value = lut[*c as usize];
+            if value > 3 {
+                value = substitute;
+                invalid |= 1 << ((i % 2) * 4 + j);
+            }
+            byte |= value << (j * 2);
+        }
+        *out = byte;
+
+        if i % 2 == 1 || i * 4 + 4 >= code.len() {
+            if let Some(mask) = mask.as_deref_mut() {
+                mask[i / 2] = invalid;
+            }
+            invalid = 0;
+        }
+    }
+}
+
+/// The scalar 2-bit packer.
+#[repr(align(64))]
+pub struct TwoBitPacker {
+    lut: [u8; 256],
+}
+
+impl Default for TwoBitPacker {
+    /// A packer for ASCII input.
+    fn default() -> Self {
+        Self { lut: ASCII_TO_CODE }
+    }
+}
+
+impl TwoBitPacker {
+    /// A packer for the 0..3 codes emitted by the [`CodeConverter`]s.
+    pub fn from_codes() -> Self {
+        Self { lut: CODE_TO_CODE }
+    }
+}
+
+impl TwoBitPack for TwoBitPacker {
+    fn pack_raw(&self, code: &[u8], out: &mut [u8], mask: Option<&mut [u8]>, substitute: u8) {
+        if let Some(mask) = &mask {
+            assert!(mask.len() >= code.len().div_ceil(8));
+        }
+        pack_scalar(&self.lut, code, out, mask, substitute);
+    }
+}
+
+impl CodeConverter for TwoBitPacker {
+    /// Pack `code` into `out`, bytes that are not bases become A.
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        self.pack_substitute(code, out, 0);
+    }
+}
+
+/// The scalar 2-bit unpacker.
+///
+/// Writes exactly `out.len()` bases, so the input must hold at least `out.len().div_ceil(4)` bytes.
+pub struct TwoBitUnpacker {
+    lut: [u8; 4],
+}
+
+impl Default for TwoBitUnpacker {
+    /// An unpacker to upper case ASCII.
+    fn default() -> Self {
+        Self { lut: *b"ATCG" }
+    }
+}
+
+impl TwoBitUnpacker {
+    /// An unpacker that writes `lut[code]` for each base, in A, T, C, G order.
+    pub const fn new(lut: [u8; 4]) -> Self {
+        Self { lut }
+    }
+
+    /// An unpacker to the 0..3 codes emitted by the [`CodeConverter`]s.
+    pub fn to_codes() -> Self {
+        Self { lut: [0, 1, 2, 3] }
+    }
+}
+
+impl CodeConverter for TwoBitUnpacker {
+    fn convert(&self, input: &[u8], out: &mut [u8]) {
+        assert!(input.len() >= out.len().div_ceil(4));
+
+        for (packed, chunk) in input.iter().zip(out.chunks_mut(4)) {
+            for (j, b) in chunk.iter_mut().enumerate() {
+                *b = self.lut[((packed >> (j * 2)) & 0b11) as usize];
+            }
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(x86_avx2, "avx2");
+
+/// The AVX2 2-bit packer, falls back to [`TwoBitPacker`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub struct AVX2TwoBitPacker {
+    from_codes: bool,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for AVX2TwoBitPacker {
+    /// A packer for ASCII input.
+    fn default() -> Self {
+        Self { from_codes: false }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl AVX2TwoBitPacker {
+    /// A packer for the 0..3 codes emitted by the [`CodeConverter`]s.
+    pub fn from_codes() -> Self {
+        Self { from_codes: true }
+    }
+
+    fn scalar(&self) -> TwoBitPacker {
+        if self.from_codes {
+            TwoBitPacker::from_codes()
+        } else {
+            TwoBitPacker::default()
+        }
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn pack_impl(&self, code: &[u8], out: &mut [u8], mut mask: Option<&mut [u8]>, substitute: u8) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        assert!(out.len() >= code.len().div_ceil(4));
+        if let Some(mask) = &mask {
+            assert!(mask.len() >= code.len().div_ceil(8));
+        }
+
+        let simd_len = code.len() / 32;
+        let (code_simd, code_rest) = code.split_at(simd_len * 32);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 8);
+
+        unsafe {
+            let (a, t, c, g, tolower) = (
+                _mm256_set1_epi8(b'a' as _),
+                _mm256_set1_epi8(b't' as _),
+                _mm256_set1_epi8(b'c' as _),
+                _mm256_set1_epi8(b'g' as _),
+                _mm256_set1_epi8(0x20),
+            );
+            let (one, two, three) = (
+                _mm256_set1_epi8(1),
+                _mm256_set1_epi8(2),
+                _mm256_set1_epi8(3),
+            );
+            let substitute = _mm256_set1_epi8(substitute as _);
+            // bytes 0, 4, 8 and 12 of each lane, then the first dword of each lane
+            let gather = _mm256_setr_epi8(
+                0, 4, 8, 12, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, //
+                0, 4, 8, 12, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
+            );
+
+            for (i, (chunk, out_chunk)) in code_simd
+                .chunks_exact(32)
+                .zip(out_simd.chunks_exact_mut(8))
+                .enumerate()
+            {
+                let chunk = _mm256_loadu_si256(chunk.as_ptr().cast());
+
+                let (codes, valid) = if self.from_codes {
+                    let valid = _mm256_cmpeq_epi8(_mm256_min_epu8(chunk, three), chunk);
+                    (_mm256_and_si256(chunk, valid), valid)
+                } else {
+                    let lower = _mm256_or_si256(chunk, tolower);
+                    let is_a = _mm256_cmpeq_epi8(lower, a);
+                    let is_t = _mm256_cmpeq_epi8(lower, t);
+                    let is_c = _mm256_cmpeq_epi8(lower, c);
+                    let is_g = _mm256_cmpeq_epi8(lower, g);
+                    let codes = _mm256_or_si256(
+                        _mm256_or_si256(_mm256_and_si256(is_t, one), _mm256_and_si256(is_c, two)),
+                        _mm256_and_si256(is_g, three),
+                    );
+                    let valid =
+                        _mm256_or_si256(_mm256_or_si256(is_a, is_t), _mm256_or_si256(is_c, is_g));
+                    (codes, valid)
+                };
+                let codes = _mm256_or_si256(codes, _mm256_andnot_si256(valid, substitute));
+
+                // c0 + 4 * c1 in each word, then (c0 + 4 * c1) + 16 * (c2 + 4 * c3) in each dword
+                let words = _mm256_maddubs_epi16(codes, _mm256_set1_epi16(0x0401));
+                let dwords = _mm256_madd_epi16(words, _mm256_set1_epi32(0x0010_0001));
+                let packed = _mm256_permutevar8x32_epi32(
+                    _mm256_shuffle_epi8(dwords, gather),
+                    _mm256_setr_epi32(0, 4, 0, 0, 0, 0, 0, 0),
+                );
+
+                out_chunk
+                    .as_mut_ptr()
+                    .cast::<i64>()
+                    .write_unaligned(_mm_cvtsi128_si64(_mm256_castsi256_si128(packed)));
+
+                if let Some(mask) = mask.as_deref_mut() {
+                    let invalid = !(_mm256_movemask_epi8(valid) as u32);
+                    mask[i * 4..i * 4 + 4].copy_from_slice(&invalid.to_le_bytes());
+                }
+            }
+        }
+
+        let scalar = self.scalar();
+        pack_scalar(
+            &scalar.lut,
+            code_rest,
+            out_rest,
+            mask.map(|mask| &mut mask[simd_len * 4..]),
+            substitute,
+        );
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl TwoBitPack for AVX2TwoBitPacker {
+    fn pack_raw(&self, code: &[u8], out: &mut [u8], mask: Option<&mut [u8]>, substitute: u8) {
+        if x86_avx2::get() {
+            unsafe { self.pack_impl(code, out, mask, substitute) }
+        } else {
+            self.scalar().pack_raw(code, out, mask, substitute)
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl CodeConverter for AVX2TwoBitPacker {
+    /// Pack `code` into `out`, bytes that are not bases become A.
+    fn convert(&self, code: &[u8], out: &mut [u8]) {
+        self.pack_substitute(code, out, 0);
+    }
+}
+
+/// The AVX2 2-bit unpacker, falls back to [`TwoBitUnpacker`].
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub struct AVX2TwoBitUnpacker {
+    lut: [u8; 4],
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Default for AVX2TwoBitUnpacker {
+    /// An unpacker to upper case ASCII.
+    fn default() -> Self {
+        Self { lut: *b"ATCG" }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl AVX2TwoBitUnpacker {
+    /// An unpacker that writes `lut[code]` for each base, in A, T, C, G order.
+    pub const fn new(lut: [u8; 4]) -> Self {
+        Self { lut }
+    }
+
+    /// An unpacker to the 0..3 codes emitted by the [`CodeConverter`]s.
+    pub fn to_codes() -> Self {
+        Self { lut: [0, 1, 2, 3] }
+    }
+
+    #[target_feature(enable = "avx2")]
+    fn convert_impl(&self, input: &[u8], out: &mut [u8]) {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::*;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::*;
+
+        assert!(input.len() >= out.len().div_ceil(4));
+
+        let simd_len = out.len() / 32;
+        let (input_simd, input_rest) = input.split_at(simd_len * 8);
+        let (out_simd, out_rest) = out.split_at_mut(simd_len * 32);
+
+        // each base is isolated in its own byte as 0b11 << (2 * position), position 0 and 1
+        // index this table directly and position 2 and 3 after a shift by 4
+        let [o0, o1, o2, o3] = self.lut.map(|b| b as i8);
+        unsafe {
+            let lut = _mm256_setr_epi8(
+                o0, o1, o2, o3, o1, 0, 0, 0, o2, 0, 0, 0, o3, 0, 0, 0, //
+                o0, o1, o2, o3, o1, 0, 0, 0, o2, 0, 0, 0, o3, 0, 0, 0,
+            );
+            let spread = _mm256_setr_epi8(
+                0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, //
+                4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7,
+            );
+            let position_mask = _mm256_set1_epi32(0xC030_0C03u32 as i32);
+            let upper_half = _mm256_set1_epi32(0xFFFF_0000u32 as i32);
+            let low_nibble = _mm256_set1_epi8(0b0000_1111);
+
+            for (chunk, out_chunk) in input_simd
+                .chunks_exact(8)
+                .zip(out_simd.chunks_exact_mut(32))
+            {
+                let packed = _mm256_set1_epi64x(chunk.as_ptr().cast::<i64>().read_unaligned());
+                let isolated = _mm256_and_si256(_mm256_shuffle_epi8(packed, spread), position_mask);
+                let shifted = _mm256_and_si256(_mm256_srli_epi16(isolated, 4), low_nibble);
+                let indices = _mm256_blendv_epi8(isolated, shifted, upper_half);
+
+                _mm256_storeu_si256(
+                    out_chunk.as_mut_ptr().cast(),
+                    _mm256_shuffle_epi8(lut, indices),
+                );
+            }
+        }
+
+        TwoBitUnpacker { lut: self.lut }.convert(input_rest, out_rest);
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl CodeConverter for AVX2TwoBitUnpacker {
+    fn convert(&self, input: &[u8], out: &mut [u8]) {
+        if x86_avx2::get() {
+            unsafe { self.convert_impl(input, out) }
+        } else {
+            TwoBitUnpacker { lut: self.lut }.convert(input, out)
+        }
+    }
+}
+
+/// A run of identical bases that don't fit into 2 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Exception {
+    /// Offset of the first base of the run.
+    pub position: usize,
+    pub length: usize,
+    /// The IUPAC mask shared by the bases of the run, as produced by [`custom_alphabet`](crate::custom_alphabet).
+    pub mask: u8,
+}
+
+/// A dense 2-bit sequence plus a sorted list of the runs that are not A, C, G or T,
+/// similar to the N blocks of the UCSC .2bit format.
+///
+/// Exception bases are stored as A in the packed stream. Case is not preserved, and bytes that
+/// are not IUPAC codes decode as N.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExceptionSequence {
+    len: usize,
+    packed: Vec<u8>,
+    exceptions: Vec<Exception>,
+}
+
+#[cfg(feature = "alloc")]
+impl ExceptionSequence {
+    /// Encode ASCII `code` with [`TwoBitPacker`].
+    pub fn encode(code: &[u8]) -> Self {
+        Self::encode_with(&TwoBitPacker::default(), code)
+    }
+
+    /// Encode ASCII `code` with `packer`.
+    pub fn encode_with<P: TwoBitPack>(packer: &P, code: &[u8]) -> Self {
+        let mut packed = vec![0; code.len().div_ceil(4)];
+        let mut exceptions: Vec<Exception> = Vec::new();
+
+        let mut invalid = [0u8; BLOCK / 8];
+        for (i, (block, out)) in code
+            .chunks(BLOCK)
+            .zip(packed.chunks_mut(BLOCK / 4))
+            .enumerate()
+        {
+            packer.pack_raw(block, out, Some(&mut invalid), 0);
+
+            for (j, bits) in invalid[..block.len().div_ceil(8)].iter().enumerate() {
+                let mut bits = *bits;
+                while bits != 0 {
+                    let position = i * BLOCK + j * 8 + bits.trailing_zeros() as usize;
+                    let mask = BASE_CHAR_TO_MASK.0[code[position] as usize];
+                    match exceptions.last_mut() {
+                        Some(last)
+                            if last.position + last.length == position && last.mask == mask =>
+                        {
+                            last.length += 1
+                        }
+                        _ => exceptions.push(Exception {
+                            position,
+                            length: 1,
+                            mask,
+                        }),
+                    }
+                    bits &= bits - 1;
+                }
+            }
+        }
+
+        Self {
+            len: code.len(),
+            packed,
+            exceptions,
+        }
+    }
+
+    /// Build a sequence of `len` bases from its packed stream and exception list.
+    ///
+    /// Panics if `packed` is too short or if the exceptions are unsorted, overlapping or out of bounds.
+    pub fn from_parts(len: usize, packed: Vec<u8>, exceptions: Vec<Exception>) -> Self {
+        assert!(packed.len() >= len.div_ceil(4));
+        let mut end = 0;
+        for e in &exceptions {
+            assert!(e.position >= end, "exceptions must be sorted and disjoint");
+            end = e.position + e.length;
+        }
+        assert!(end <= len, "exception out of bounds");
+
+        Self {
+            len,
+            packed,
+            exceptions,
+        }
+    }
+
+    /// Number of bases.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn packed(&self) -> &[u8] {
+        &self.packed
+    }
+
+    pub fn exceptions(&self) -> &[Exception] {
+        &self.exceptions
+    }
+
+    /// Decode into upper case ASCII with [`TwoBitUnpacker`], `out` must hold exactly [`len`](Self::len) bytes.
+    pub fn decode(&self, out: &mut [u8]) {
+        self.decode_with(&TwoBitUnpacker::default(), out)
+    }
+
+    /// Decode with `unpacker`, which must unpack to ASCII.
+    pub fn decode_with<U: CodeConverter>(&self, unpacker: &U, out: &mut [u8]) {
+        assert_eq!(out.len(), self.len);
+
+        unpacker.convert(&self.packed, out);
+        for e in &self.exceptions {
+            out[e.position..e.position + e.length].fill(BASE_MASK_TO_CHAR.0[e.mask as usize]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NaiveCodeConverter;
+    use rand::{Rng, SeedableRng};
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    const LENGTHS: [usize; 12] = [0, 1, 3, 4, 5, 31, 32, 33, 63, 100, 4097, 100_000];
+
+    fn random_sequence(rng: &mut impl Rng, n: usize, alphabet: &[u8]) -> Vec<u8> {
+        (0..n)
+            .map(|_| alphabet[rng.random_range(0..alphabet.len())])
+            .collect()
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn test_packer<P: TwoBitPack>(packer: &P, from_codes: bool) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let reference = if from_codes {
+            TwoBitPacker::from_codes()
+        } else {
+            TwoBitPacker::default()
+        };
+        let alphabet: &[u8] = if from_codes {
+            &[0, 1, 2, 3, 0, 1, 2, 3, 4, 255]
+        } else {
+            b"ATCGatcgNnRx"
+        };
+        for n in LENGTHS {
+            let code = random_sequence(&mut rng, n, alphabet);
+
+            let mut expected = vec![0; n.div_ceil(4)];
+            let mut expected_mask = vec![0; n.div_ceil(8)];
+            reference.pack_masked(&code, &mut expected, &mut expected_mask);
+
+            let mut out = vec![0; n.div_ceil(4)];
+            let mut mask = vec![0xAA; n.div_ceil(8)];
+            packer.pack_masked(&code, &mut out, &mut mask);
+            assert_eq!(out, expected, "packed mismatch for length {}", n);
+            assert_eq!(mask, expected_mask, "mask mismatch for length {}", n);
+
+            reference.pack_substitute(&code, &mut expected, 2);
+            packer.pack_substitute(&code, &mut out, 2);
+            assert_eq!(out, expected, "substituted mismatch for length {}", n);
+
+            assert_eq!(
+                packer.try_pack(&code, &mut out),
+                reference.try_pack(&code, &mut expected)
+            );
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    fn test_unpacker<U: CodeConverter>(unpacker: &U, reference: &TwoBitUnpacker) {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for n in LENGTHS {
+            let packed: Vec<u8> = (0..n.div_ceil(4)).map(|_| rng.random()).collect();
+
+            let mut expected = vec![0; n];
+            let mut out = vec![0; n];
+            reference.convert(&packed, &mut expected);
+            unpacker.convert(&packed, &mut out);
+            assert_eq!(out, expected, "mismatch for length {}", n);
+        }
+    }
+
+    #[test]
+    fn test_pack_layout() {
+        let mut out = [0; 2];
+        assert_eq!(TwoBitPacker::default().try_pack(b"ATCGg", &mut out), Ok(2));
+        assert_eq!(out, [0b11_10_01_00, 0b11]);
+
+        let mut unpacked = [0; 5];
+        TwoBitUnpacker::default().convert(&out, &mut unpacked);
+        assert_eq!(&unpacked, b"ATCGG");
+    }
+
+    #[test]
+    fn test_try_pack_reports_first_invalid() {
+        let mut code = vec![b'A'; 10_000];
+        code[5000] = b'N';
+        code[9000] = b'-';
+        let mut out = vec![0; 2500];
+        assert_eq!(
+            TwoBitPacker::default().try_pack(&code, &mut out),
+            Err(InvalidBase {
+                position: 5000,
+                byte: b'N'
+            })
+        );
+    }
+
+    #[test]
+    fn test_masked_round_trip() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let code = random_sequence(&mut rng, 1001, b"ACGTN");
+
+        let mut packed = vec![0; code.len().div_ceil(4)];
+        let mut mask = vec![0; code.len().div_ceil(8)];
+        TwoBitPacker::default().pack_masked(&code, &mut packed, &mut mask);
+
+        let mut unpacked = vec![0; code.len()];
+        TwoBitUnpacker::default().convert(&packed, &mut unpacked);
+        apply_mask(&mask, &mut unpacked, b'N');
+        assert_eq!(unpacked, code);
+    }
+
+    #[test]
+    fn test_codes_round_trip() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let code = random_sequence(&mut rng, 1001, b"ACGTacgt");
+        let mut codes = vec![0; code.len()];
+        NaiveCodeConverter::default().convert(&code, &mut codes);
+
+        let mut packed = vec![0; code.len().div_ceil(4)];
+        let mut from_ascii = vec![0; code.len().div_ceil(4)];
+        TwoBitPacker::from_codes()
+            .try_pack(&codes, &mut packed)
+            .unwrap();
+        TwoBitPacker::default()
+            .try_pack(&code, &mut from_ascii)
+            .unwrap();
+        assert_eq!(packed, from_ascii);
+
+        let mut unpacked = vec![0; code.len()];
+        TwoBitUnpacker::to_codes().convert(&packed, &mut unpacked);
+        assert_eq!(unpacked, codes);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_exception_sequence() {
+        use crate::custom_alphabet::{K, N, R, Y};
+
+        let seq = ExceptionSequence::encode(b"ACNNNNGTrYYk-");
+        assert_eq!(seq.len(), 13);
+        assert_eq!(
+            seq.exceptions(),
+            &[
+                Exception {
+                    position: 2,
+                    length: 4,
+                    mask: N
+                },
+                Exception {
+                    position: 8,
+                    length: 1,
+                    mask: R
+                },
+                Exception {
+                    position: 9,
+                    length: 2,
+                    mask: Y
+                },
+                Exception {
+                    position: 11,
+                    length: 1,
+                    mask: K
+                },
+                Exception {
+                    position: 12,
+                    length: 1,
+                    mask: N
+                },
+            ]
+        );
+
+        let mut out = vec![0; seq.len()];
+        seq.decode(&mut out);
+        assert_eq!(&out, b"ACNNNNGTRYYKN");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_exception_sequence_round_trip() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        for n in [0, 1, 7, 4095, 4096, 4097, 100_000] {
+            let mut code = random_sequence(&mut rng, n, b"ACGT");
+            // long N runs crossing block boundaries, and sparse ambiguity codes
+            for _ in 0..n / 1000 {
+                let start = rng.random_range(0..n);
+                let end = (start + rng.random_range(1..5000)).min(n);
+                code[start..end].fill(b'N');
+            }
+            for _ in 0..n / 100 {
+                code[rng.random_range(0..n)] = b"RYSWKMBDHV"[rng.random_range(0..10)];
+            }
+
+            let seq = ExceptionSequence::encode(&code);
+            assert_eq!(seq.packed().len(), n.div_ceil(4));
+            let mut out = vec![0; n];
+            seq.decode(&mut out);
+            assert_eq!(out, code);
+
+            assert_eq!(
+                ExceptionSequence::from_parts(
+                    seq.len(),
+                    seq.packed().to_vec(),
+                    seq.exceptions().to_vec(),
+                ),
+                seq
+            );
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                assert_eq!(
+                    ExceptionSequence::encode_with(&AVX2TwoBitPacker::default(), &code),
+                    seq
+                );
+                seq.decode_with(&AVX2TwoBitUnpacker::default(), &mut out);
+                assert_eq!(out, code);
+            }
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_avx2_matches_scalar() {
+        test_packer(&AVX2TwoBitPacker::default(), false);
+        test_packer(&AVX2TwoBitPacker::from_codes(), true);
+        test_unpacker(&AVX2TwoBitUnpacker::default(), &TwoBitUnpacker::default());
+        test_unpacker(&AVX2TwoBitUnpacker::to_codes(), &TwoBitUnpacker::to_codes());
+    }
+}
@@ -0,0 +1,548 @@
+//! UCSC `.2bit` files.
+//!
+//! A `.2bit` file packs 4 bases per byte with T = 0, C = 1, A = 2 and G = 3, the first base in
+//! the two highest bits. Runs of N and of lowercase (soft-masked) bases are stored as separate
+//! block tables per sequence, and an index at the start of the file gives the offset of every
+//! sequence, so any region can be read without decoding the rest of the file.
+
+use core::ops::Range;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::custom_alphabet::{A, C, G, N, T};
+use crate::invalid_data;
+use crate::packed::PackedSlice;
+
+const SIGNATURE: u32 = 0x1A41_2743;
+
+/// ASCII of the 4 bases in every packed byte.
+const BYTE_TO_ASCII: [[u8; 4]; 256] = {
+    let mut lut = [[0; 4]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut i = 0;
+        while i < 4 {
+            lut[byte][i] = b"TCAG"[(byte >> (6 - 2 * i)) & 0b11];
+            i += 1;
+        }
+        byte += 1;
+    }
+    lut
+};
+
+/// Nibble masks of the 2-bit codes.
+const CODE_TO_NIBBLE: [u8; 4] = [T, C, A, G];
+
+/// The N and mask blocks of a sequence, and where its packed bases start.
+#[derive(Debug, Clone)]
+struct SequenceRecord {
+    len: usize,
+    n_blocks: Vec<Range<usize>>,
+    mask_blocks: Vec<Range<usize>>,
+    dna_offset: u64,
+}
+
+/// The blocks overlapping `range`, clipped to it. The blocks are sorted and do not overlap.
+fn overlapping(
+    blocks: &[Range<usize>],
+    range: Range<usize>,
+) -> impl Iterator<Item = Range<usize>> + '_ {
+    let first = blocks.partition_point(|block| block.end <= range.start);
+    blocks[first..]
+        .iter()
+        .take_while(move |block| block.start < range.end)
+        .map(move |block| block.start.max(range.start)..block.end.min(range.end))
+}
+
+/// Whether `blocks` are sorted, do not overlap and end within `len` bases, as [`overlapping`]
+/// expects.
+fn valid_blocks(blocks: &[Range<usize>], len: usize) -> bool {
+    blocks.windows(2).all(|pair| pair[0].end <= pair[1].start)
+        && blocks.last().is_none_or(|block| block.end <= len)
+}
+
+/// Reads sequences and regions from a `.2bit` file.
+pub struct TwoBitFileReader<R> {
+    reader: R,
+    big_endian: bool,
+    names: Vec<Vec<u8>>,
+    offsets: Vec<u64>,
+    index: HashMap<Vec<u8>, usize>,
+    /// The last record read, most queries hit the same sequence.
+    cached: Option<(usize, SequenceRecord)>,
+}
+
+impl<R: Read + Seek> TwoBitFileReader<R> {
+    /// Read the header and the index of `reader`.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut word = [0; 4];
+        reader.read_exact(&mut word)?;
+        let big_endian = if u32::from_le_bytes(word) == SIGNATURE {
+            false
+        } else if u32::from_be_bytes(word) == SIGNATURE {
+            true
+        } else {
+            return Err(invalid_data("not a 2bit file"));
+        };
+
+        let mut file = Self {
+            reader,
+            big_endian,
+            names: Vec::new(),
+            offsets: Vec::new(),
+            index: HashMap::new(),
+            cached: None,
+        };
+        let version = file.read_u32()?;
+        if version > 1 {
+            return Err(invalid_data("unsupported 2bit version"));
+        }
+        let count = file.read_u32()? as usize;
+        file.read_u32()?;
+
+        for i in 0..count {
+            let mut len = [0];
+            file.reader.read_exact(&mut len)?;
+            let mut name = vec![0; len[0] as usize];
+            file.reader.read_exact(&mut name)?;
+            let offset = if version == 1 {
+                file.read_u64()?
+            } else {
+                file.read_u32()? as u64
+            };
+            file.index.insert(name.clone(), i);
+            file.names.push(name);
+            file.offsets.push(offset);
+        }
+        Ok(file)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The sequence names in file order.
+    pub fn names(&self) -> impl ExactSizeIterator<Item = &[u8]> {
+        self.names.iter().map(Vec::as_slice)
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut word = [0; 4];
+        self.reader.read_exact(&mut word)?;
+        Ok(if self.big_endian {
+            u32::from_be_bytes(word)
+        } else {
+            u32::from_le_bytes(word)
+        })
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut word = [0; 8];
+        self.reader.read_exact(&mut word)?;
+        Ok(if self.big_endian {
+            u64::from_be_bytes(word)
+        } else {
+            u64::from_le_bytes(word)
+        })
+    }
+
+    fn read_blocks(&mut self) -> io::Result<Vec<Range<usize>>> {
+        let count = self.read_u32()?;
+        // grown as the starts are read, the count may be corrupt
+        let mut starts = Vec::new();
+        for _ in 0..count {
+            starts.push(self.read_u32()? as usize);
+        }
+        let mut blocks = Vec::with_capacity(starts.len());
+        for start in starts {
+            let end = start.checked_add(self.read_u32()? as usize);
+            blocks.push(
+                start
+                    ..end.ok_or_else(|| {
+                        invalid_data("2bit block past the end of the address space")
+                    })?,
+            );
+        }
+        Ok(blocks)
+    }
+
+    /// Load the record of `name` into the cache and return it.
+    fn record(&mut self, name: &[u8]) -> io::Result<&SequenceRecord> {
+        let Some(&idx) = self.index.get(name) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("sequence '{}' not in 2bit file", name.escape_ascii()),
+            ));
+        };
+        if !matches!(self.cached, Some((cached, _)) if cached == idx) {
+            self.cached = Some((idx, self.read_record(idx)?));
+        }
+        Ok(&self.cached.as_ref().unwrap().1)
+    }
+
+    fn read_record(&mut self, idx: usize) -> io::Result<SequenceRecord> {
+        self.reader.seek(SeekFrom::Start(self.offsets[idx]))?;
+        let len = self.read_u32()? as usize;
+        let n_blocks = self.read_blocks()?;
+        let mask_blocks = self.read_blocks()?;
+        if !valid_blocks(&n_blocks, len) || !valid_blocks(&mask_blocks, len) {
+            return Err(invalid_data(
+                "2bit blocks out of order or past the end of the sequence",
+            ));
+        }
+        self.read_u32()?;
+        Ok(SequenceRecord {
+            len,
+            n_blocks,
+            mask_blocks,
+            dna_offset: self.reader.stream_position()?,
+        })
+    }
+
+    /// Number of bases of sequence `name`.
+    pub fn sequence_len(&mut self, name: &[u8]) -> io::Result<usize> {
+        Ok(self.record(name)?.len)
+    }
+
+    /// Read the packed bytes holding `range`, leaving the record of `name` in the cache.
+    fn read_packed(&mut self, name: &[u8], range: &Range<usize>) -> io::Result<Vec<u8>> {
+        let &SequenceRecord {
+            len, dna_offset, ..
+        } = self.record(name)?;
+        if range.start > range.end || range.end > len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "region {}..{} out of bounds for '{}' of length {}",
+                    range.start,
+                    range.end,
+                    name.escape_ascii(),
+                    len
+                ),
+            ));
+        }
+
+        let first = range.start / 4;
+        let mut packed = vec![0; range.end.div_ceil(4) - first];
+        self.reader
+            .seek(SeekFrom::Start(dna_offset + first as u64))?;
+        self.reader.read_exact(&mut packed)?;
+        Ok(packed)
+    }
+
+    /// Decode the bases of `name` in `range` into `out` as ASCII, with N blocks as `N` and
+    /// soft-masked bases in lowercase.
+    pub fn read_region(
+        &mut self,
+        name: &[u8],
+        range: Range<usize>,
+        out: &mut [u8],
+    ) -> io::Result<()> {
+        let packed = self.read_packed(name, &range)?;
+        let record = &self.cached.as_ref().unwrap().1;
+        let out = &mut out[..range.len()];
+        for (i, base) in (range.start..range.end).zip(out.iter_mut()) {
+            *base = BYTE_TO_ASCII[packed[i / 4 - range.start / 4] as usize][i % 4];
+        }
+
+        for block in overlapping(&record.n_blocks, range.clone()) {
+            out[block.start - range.start..block.end - range.start].fill(b'N');
+        }
+        for block in overlapping(&record.mask_blocks, range.clone()) {
+            out[block.start - range.start..block.end - range.start].make_ascii_lowercase();
+        }
+        Ok(())
+    }
+
+    /// Decode the bases of `name` in `range` into `out` in the
+    /// [`custom_alphabet`](crate::custom_alphabet) nibble encoding. Soft-masking is dropped.
+    pub fn read_region_packed<'a>(
+        &mut self,
+        name: &[u8],
+        range: Range<usize>,
+        out: &'a mut [u8],
+    ) -> io::Result<PackedSlice<'a>> {
+        let packed = self.read_packed(name, &range)?;
+        let record = &self.cached.as_ref().unwrap().1;
+        let out = &mut out[..range.len().div_ceil(2)];
+        out.fill(0);
+        let mut set = |i: usize, mask: u8| {
+            let j = i - range.start;
+            out[j / 2] = out[j / 2] & (0b1111_0000 >> (j % 2 * 4)) | mask << (j % 2 * 4);
+        };
+        for i in range.clone() {
+            let code = packed[i / 4 - range.start / 4] >> (6 - 2 * (i % 4)) & 0b11;
+            set(i, CODE_TO_NIBBLE[code as usize]);
+        }
+        for block in overlapping(&record.n_blocks, range.clone()) {
+            block.for_each(|i| set(i, N));
+        }
+        Ok(PackedSlice::new(out, range.len()))
+    }
+
+    /// Decode the whole sequence `name` as ASCII.
+    pub fn read_sequence(&mut self, name: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = vec![0; self.sequence_len(name)?];
+        let len = out.len();
+        self.read_region(name, 0..len, &mut out)?;
+        Ok(out)
+    }
+}
+
+struct PendingSequence {
+    name: Vec<u8>,
+    len: usize,
+    n_blocks: Vec<Range<usize>>,
+    mask_blocks: Vec<Range<usize>>,
+    packed: Vec<u8>,
+}
+
+impl PendingSequence {
+    fn record_len(&self) -> u64 {
+        let blocks = self.n_blocks.len() + self.mask_blocks.len();
+        (4 * 4 + blocks * 8 + self.packed.len()) as u64
+    }
+}
+
+/// The runs of bytes in `seq` for which `f` is true.
+fn runs(seq: &[u8], f: impl Fn(u8) -> bool) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for (i, &b) in seq.iter().enumerate() {
+        if f(b) {
+            match runs.last_mut() {
+                Some(run) if run.end == i => run.end += 1,
+                _ => runs.push(i..i + 1),
+            }
+        }
+    }
+    runs
+}
+
+/// Collects sequences and writes them as a `.2bit` file.
+///
+/// The index at the start of the file needs the size of every sequence, so the sequences are
+/// kept packed in memory until [`write`](Self::write).
+#[derive(Default)]
+pub struct TwoBitFileWriter {
+    sequences: Vec<PendingSequence>,
+}
+
+impl TwoBitFileWriter {
+    /// Add an ASCII sequence. Bytes other than ACGT in either case are stored as N, lowercase
+    /// bases are soft-masked.
+    pub fn add(&mut self, name: &[u8], seq: &[u8]) -> io::Result<()> {
+        if name.len() > u8::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "2bit sequence names are at most 255 bytes",
+            ));
+        }
+        if seq.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "2bit sequences are at most 4 Gbases",
+            ));
+        }
+
+        let mut packed = vec![0; seq.len().div_ceil(4)];
+        for (i, base) in seq.iter().enumerate() {
+            let code = match base.to_ascii_uppercase() {
+                b'C' => 1,
+                b'A' => 2,
+                b'G' => 3,
+                _ => 0,
+            };
+            packed[i / 4] |= code << (6 - 2 * (i % 4));
+        }
+        self.sequences.push(PendingSequence {
+            name: name.to_vec(),
+            len: seq.len(),
+            n_blocks: runs(seq, |b| !b"ACGTacgt".contains(&b)),
+            mask_blocks: runs(seq, |b| b.is_ascii_lowercase()),
+            packed,
+        });
+        Ok(())
+    }
+
+    /// Write the file in little endian. Version 1, with 64-bit offsets, is only used if the file
+    /// is larger than 4 GiB.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let index_len = |offset_len: usize| -> u64 {
+            self.sequences
+                .iter()
+                .map(|seq| (1 + seq.name.len() + offset_len) as u64)
+                .sum()
+        };
+        let records_len: u64 = self.sequences.iter().map(PendingSequence::record_len).sum();
+        let version = u32::from(16 + index_len(4) + records_len > u32::MAX as u64);
+        let offset_len = if version == 1 { 8 } else { 4 };
+
+        writer.write_all(&SIGNATURE.to_le_bytes())?;
+        writer.write_all(&version.to_le_bytes())?;
+        writer.write_all(&(self.sequences.len() as u32).to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        let mut offset = 16 + index_len(offset_len);
+        for seq in &self.sequences {
+            writer.write_all(&[seq.name.len() as u8])?;
+            writer.write_all(&seq.name)?;
+            if version == 1 {
+                writer.write_all(&offset.to_le_bytes())?;
+            } else {
+                writer.write_all(&(offset as u32).to_le_bytes())?;
+            }
+            offset += seq.record_len();
+        }
+
+        for seq in &self.sequences {
+            writer.write_all(&(seq.len as u32).to_le_bytes())?;
+            for blocks in [&seq.n_blocks, &seq.mask_blocks] {
+                writer.write_all(&(blocks.len() as u32).to_le_bytes())?;
+                for block in blocks.iter() {
+                    writer.write_all(&(block.start as u32).to_le_bytes())?;
+                }
+                for block in blocks.iter() {
+                    writer.write_all(&(block.len() as u32).to_le_bytes())?;
+                }
+            }
+            writer.write_all(&0u32.to_le_bytes())?;
+            writer.write_all(&seq.packed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CodeConverter;
+    use crate::custom_alphabet::LUTPacker;
+    use rand::{Rng, SeedableRng};
+    use std::io::Cursor;
+
+    /// Random sequences with runs of N and of lowercase bases.
+    fn synthetic_genome(rng: &mut impl Rng) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut genome = Vec::new();
+        for (i, len) in [0, 1, 3, 4, 5, 1000, 10_007].into_iter().enumerate() {
+            let mut seq: Vec<u8> = (0..len).map(|_| b"ACGT"[rng.random_range(0..4)]).collect();
+            for _ in 0..len / 100 {
+                let start = rng.random_range(0..len);
+                let end = (start + rng.random_range(1..200)).min(len);
+                if rng.random() {
+                    seq[start..end].fill(b'N');
+                } else {
+                    seq[start..end].make_ascii_lowercase();
+                }
+            }
+            genome.push((format!("chr{}", i).into_bytes(), seq));
+        }
+        genome
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let genome = synthetic_genome(&mut rng);
+        let mut writer = TwoBitFileWriter::default();
+        for (name, seq) in &genome {
+            writer.add(name, seq).unwrap();
+        }
+        let mut file = Vec::new();
+        writer.write(&mut file).unwrap();
+
+        let mut reader = TwoBitFileReader::new(Cursor::new(&file)).unwrap();
+        assert!(reader.names().eq(genome.iter().map(|(name, _)| &name[..])));
+        for (name, seq) in &genome {
+            assert_eq!(&reader.read_sequence(name).unwrap(), seq);
+        }
+
+        // random regions, alternating between sequences
+        for _ in 0..500 {
+            let (name, seq) = &genome[rng.random_range(0..genome.len())];
+            let start = rng.random_range(0..=seq.len());
+            let end = rng.random_range(start..=seq.len());
+
+            let mut out = vec![0; end - start];
+            reader.read_region(name, start..end, &mut out).unwrap();
+            assert_eq!(out, seq[start..end]);
+
+            let mut expected = vec![0; (end - start).div_ceil(2)];
+            LUTPacker::default().convert(&seq[start..end], &mut expected);
+            let mut out = vec![0xff; (end - start).div_ceil(2)];
+            let packed = reader
+                .read_region_packed(name, start..end, &mut out)
+                .unwrap();
+            assert_eq!(packed.as_bytes(), expected);
+        }
+
+        let mut out = [0; 2];
+        let err = reader.read_region(b"chr2", 2..4, &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = reader.read_region(b"chrX", 0..0, &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut writer = TwoBitFileWriter::default();
+        writer.add(b"s", b"TCAGgnnA").unwrap();
+        let mut file = Vec::new();
+        writer.write(&mut file).unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x43, 0x27, 0x41, 0x1a, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            // index
+            1, b's', 22, 0, 0, 0,
+            // 8 bases, one N block at 5..7, one mask block at 4..7
+            8, 0, 0, 0,
+            1, 0, 0, 0, 5, 0, 0, 0, 2, 0, 0, 0,
+            1, 0, 0, 0, 4, 0, 0, 0, 3, 0, 0, 0,
+            0, 0, 0, 0,
+            0b00_01_10_11, 0b11_00_00_10,
+        ];
+        assert_eq!(file, expected);
+
+        // the same file in big endian
+        let mut big = file.clone();
+        for word in [0, 4, 8, 12, 18, 22, 26, 30, 34, 38, 42, 46, 50] {
+            big[word..word + 4].reverse();
+        }
+        let mut reader = TwoBitFileReader::new(Cursor::new(big)).unwrap();
+        assert_eq!(reader.read_sequence(b"s").unwrap(), b"TCAGgnnA");
+    }
+
+    #[test]
+    fn test_malformed_blocks() {
+        #[rustfmt::skip]
+        let header: &[u8] = &[
+            0x43, 0x27, 0x41, 0x1a, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            1, b's', 22, 0, 0, 0,
+            8, 0, 0, 0,
+        ];
+
+        // N blocks at 4..6 and 0..2, out of order
+        let mut file = header.to_vec();
+        file.extend_from_slice(&[2, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0]);
+        file.extend_from_slice(&[0; 4 + 4 + 2]);
+        let mut reader = TwoBitFileReader::new(Cursor::new(file)).unwrap();
+        let err = reader.read_sequence(b"s").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a mask block past the end of the sequence
+        let mut file = header.to_vec();
+        file.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 6, 0, 0, 0, 3, 0, 0, 0]);
+        file.extend_from_slice(&[0; 4 + 2]);
+        let mut reader = TwoBitFileReader::new(Cursor::new(file)).unwrap();
+        let err = reader.read_sequence(b"s").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a truncated file claiming 2^32 - 1 N blocks fails without reserving room for them
+        let mut file = header.to_vec();
+        file.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+        let mut reader = TwoBitFileReader::new(Cursor::new(file)).unwrap();
+        let err = reader.read_sequence(b"s").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}